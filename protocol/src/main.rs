@@ -1,7 +1,11 @@
 use clap::Parser;
 use std::error::Error;
 use tokio::io::AsyncWriteExt;
-use zk_cp_protocol::protocol::cp::{Material, MaterialSerde};
+use zk_cp_protocol::protocol::cp::{
+    Material, MaterialSerde, DEFAULT_H_SEED, DEFAULT_SECURITY_BITS,
+};
+use zk_cp_protocol::protocol::groups::NamedGroup;
+use zk_cp_protocol::protocol::password;
 
 #[derive(Debug, Parser)]
 #[clap(
@@ -18,6 +22,23 @@ pub struct GenMaterial {
 
     #[arg(short, long, default_value = "user")]
     user: String,
+
+    #[arg(short, long, default_value_t = DEFAULT_SECURITY_BITS, help = "Bit length of the safe prime p")]
+    bits: usize,
+
+    #[arg(
+        short,
+        long,
+        help = "Use a well-known RFC 3526 or RFC 7919 group (e.g. ffdhe2048) instead of generating one"
+    )]
+    group: Option<NamedGroup>,
+
+    #[arg(
+        long,
+        default_value = DEFAULT_H_SEED,
+        help = "Public seed from which the generator h is derived"
+    )]
+    h_seed: String,
 }
 
 fn init_tracing() {
@@ -34,11 +55,22 @@ fn init_tracing() {
 async fn main() -> Result<(), Box<dyn Error>> {
     let conf = GenMaterial::parse();
     init_tracing();
-    tracing::info!("Generated random material ... ");
-    let material = Material::default();
+    let material = match conf.group {
+        Some(group) => {
+            tracing::info!("Using named group {} ... ", group);
+            Material::from_named_group(group)
+        }
+        None => {
+            tracing::info!("Generating random material of {} bits ... ", conf.bits);
+            Material::generate(conf.bits, None)?
+        }
+    }
+    .with_h_seed(&conf.h_seed);
     let mut client_file = tokio::fs::File::create(conf.client_output_file.clone()).await?;
     let mut server_file = tokio::fs::File::create(conf.server_output_file.clone()).await?;
-    let material_serde = MaterialSerde::from_material(&material, conf.user.as_str());
+    let salt = password::generate_salt(&mut rand::thread_rng());
+    let material_serde =
+        MaterialSerde::from_material(&material, conf.user.as_str()).with_salt(&salt);
     let client_s = serde_json::to_string(&material_serde)?;
     client_file.write_all(client_s.as_bytes()).await?;
     client_file.write_all(b"\n").await?;
@@ -0,0 +1,226 @@
+//! This module contains the batch verifier of the Chaum-Pedersen ZK protocol.
+//!
+//! Each `Verification` checks `r_j = b_j^s * y_j^c` for each of its bases `b_j`, `r1 = g^s * y1^c` and
+//! `r2 = h^s * y2^c` with the default bases. Instead of checking every equation on its own, the batch verifier draws a
+//! random 128-bit weight `a_ij` per equation, reduced modulo `q`, and checks a single random linear combination of all
+//! of them:
+//!
+//! `Π_j b_j^(Σ_i a_ij s_i) * Π_ij y_ij^(a_ij c_i) = Π_ij r_ij^(a_ij)`
+//!
+//! Both sides are computed with `Group::multi_exp`, so every base is exponentiated once for the whole batch and the
+//! commitments only with short exponents. If any of the proofs is invalid, the combined check passes with probability
+//! at most about `max(2^-127, 1/q)`: the weights have 127 random bits, and only `q` distinct values once reduced for a
+//! group whose `q` is shorter than that. When the combined check fails, the batch is split in halves which are
+//! checked recursively, so the individual failing proofs are found.
+//!
+//! The values of every `Verification` are validated first: proofs with values outside of the group are rejected on
+//! their own and never enter the combined check. Proofs over different groups or bases are batched separately.
+use crate::protocol::cp::{Verification, VerificationResult};
+use crate::protocol::group::Group;
+use rand::{CryptoRng, Rng, RngCore};
+use std::collections::HashMap;
+
+/// Verifies many proofs at once.
+///
+/// # Arguments
+///
+/// * `verifications` - The proofs to verify.
+///
+/// # Returns
+///
+/// The `VerificationResult` of every proof, in the same order as `verifications`. They are the same results
+/// `ProtocolState<Verification>::change` would return for each of them.
+pub fn verify_batch<G: Group>(verifications: &[Verification<G>]) -> Vec<VerificationResult> {
+    verify_batch_with_rng(verifications, &mut rand::thread_rng())
+}
+
+/// Same as `verify_batch`, drawing the weights from the given RNG.
+pub fn verify_batch_with_rng<G: Group, R: RngCore + CryptoRng>(
+    verifications: &[Verification<G>],
+    rng: &mut R,
+) -> Vec<VerificationResult> {
+    let mut results = vec![VerificationResult::ChallengeVerificationFailed; verifications.len()];
+    let mut batches: HashMap<Vec<u8>, Vec<usize>> = HashMap::new();
+    for (i, verification) in verifications.iter().enumerate() {
+        match verification.validate() {
+            Ok(()) => batches.entry(batch_key(verification)).or_default().push(i),
+            Err(reason) => results[i] = VerificationResult::ChallengeRejected(reason),
+        }
+    }
+    for indices in batches.values() {
+        let batch: Vec<&Verification<G>> = indices.iter().map(|i| &verifications[*i]).collect();
+        for (i, verified) in indices.iter().zip(verify_subset(&batch, rng)) {
+            if verified {
+                results[*i] = VerificationResult::ChallengeVerifiedSuccess;
+            }
+        }
+    }
+    tracing::info!(
+        "Batch of {} proofs verified, {} failed",
+        verifications.len(),
+        results
+            .iter()
+            .filter(|r| **r != VerificationResult::ChallengeVerifiedSuccess)
+            .count()
+    );
+    results
+}
+
+/// Checks a batch of validated proofs over the same group, and bisects it when the combined check fails.
+fn verify_subset<G: Group, R: RngCore + CryptoRng>(
+    batch: &[&Verification<G>],
+    rng: &mut R,
+) -> Vec<bool> {
+    if batch.is_empty() {
+        return Vec::new();
+    }
+    if combined_check(batch, rng) {
+        return vec![true; batch.len()];
+    }
+    if batch.len() == 1 {
+        return vec![false];
+    }
+    let (left, right) = batch.split_at(batch.len() / 2);
+    let mut verified = verify_subset(left, rng);
+    verified.extend(verify_subset(right, rng));
+    verified
+}
+
+/// Identifies the proofs that can be checked together: the ones with the same group and the same bases.
+fn batch_key<G: Group>(verification: &Verification<G>) -> Vec<u8> {
+    let material = &verification.material;
+    let mut key = material.encode_params();
+    for base in &verification.bases {
+        key.extend_from_slice(&material.encode_element(base));
+    }
+    key
+}
+
+/// Checks a random linear combination of the verification equations of all the proofs in the batch, with 128-bit
+/// weights reduced modulo `q`.
+fn combined_check<G: Group, R: RngCore + CryptoRng>(
+    batch: &[&Verification<G>],
+    rng: &mut R,
+) -> bool {
+    let material = &batch[0].material;
+    let bases = &batch[0].bases;
+    let weights: Vec<Vec<G::Scalar>> = batch
+        .iter()
+        .map(|_| {
+            bases
+                .iter()
+                .map(|_| material.scalar_from_u128(rng.gen::<u128>() | 1))
+                .collect()
+        })
+        .collect();
+
+    let mut base_exponents = vec![material.scalar_from_u128(0); bases.len()];
+    let mut y_exponents = Vec::with_capacity(batch.len());
+    for (v, a) in batch.iter().zip(&weights) {
+        for (exponent, a_j) in base_exponents.iter_mut().zip(a) {
+            *exponent = material.scalar_add(exponent, &material.scalar_mul(a_j, &v.s));
+        }
+        let y_exponent: Vec<G::Scalar> =
+            a.iter().map(|a_j| material.scalar_mul(a_j, &v.c)).collect();
+        y_exponents.push(y_exponent);
+    }
+
+    let mut lhs_terms: Vec<_> = bases.iter().zip(&base_exponents).collect();
+    let mut rhs_terms = Vec::with_capacity(bases.len() * batch.len());
+    for ((v, a), y_exponent) in batch.iter().zip(&weights).zip(&y_exponents) {
+        lhs_terms.extend(v.ys.iter().zip(y_exponent));
+        rhs_terms.extend(v.rs.iter().zip(a));
+    }
+    material.multi_exp(&lhs_terms) == material.multi_exp(&rhs_terms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::cp::{Material, ProtocolState, ProtocolTransition, Register};
+    use crate::protocol::error::RejectionReason;
+    use crate::protocol::ristretto::RistrettoMaterial;
+
+    /// Runs the interactive protocol for a random secret, and returns the resulting `Verification`.
+    fn proof<G: Group>(material: &G) -> Verification<G> {
+        let mut rng = rand::thread_rng();
+        let x = material.random_scalar(&mut rng);
+        let c = material.random_scalar(&mut rng);
+        let register = Register::new(material.clone(), &x);
+        let commitment = ProtocolState::from(register.clone()).change().into_inner();
+        let s = material.scalar_sub(commitment.k.expose(), &material.scalar_mul(&c, &x));
+        Verification::builder()
+            .material(material.clone())
+            .ys(register.ys)
+            .rs(commitment.rs)
+            .c(c)
+            .s(s)
+            .build()
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let material = Material::generate(256, None).unwrap();
+        let verifications: Vec<_> = (0..16).map(|_| proof(&material)).collect();
+        assert_eq!(
+            verify_batch(&verifications),
+            vec![VerificationResult::ChallengeVerifiedSuccess; 16]
+        );
+        assert!(verify_batch::<Material>(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_verify_batch_failures() {
+        let material = Material::generate(256, None).unwrap();
+        let mut verifications: Vec<_> = (0..16).map(|_| proof(&material)).collect();
+        for i in [3, 4, 11] {
+            verifications[i].s = material.scalar_add(&verifications[i].s, &1.into());
+        }
+        verifications[7].rs[0] = material.p.clone() - 1;
+
+        let results = verify_batch(&verifications);
+        for (i, result) in results.into_iter().enumerate() {
+            let expected = match i {
+                3 | 4 | 11 => VerificationResult::ChallengeVerificationFailed,
+                7 => VerificationResult::ChallengeRejected(RejectionReason::NotInGroup("r1")),
+                _ => VerificationResult::ChallengeVerifiedSuccess,
+            };
+            assert_eq!(result, expected, "proof {}", i);
+        }
+    }
+
+    #[test]
+    fn test_verify_batch_mixed_groups() {
+        let small = Material::generate(256, None).unwrap();
+        let large = Material::generate(512, None).unwrap();
+        let mut verifications: Vec<_> = (0..8)
+            .map(|i| proof(if i % 2 == 0 { &small } else { &large }))
+            .collect();
+        verifications[5].c = large.scalar_add(&verifications[5].c, &1.into());
+
+        let results = verify_batch(&verifications);
+        for (i, result) in results.into_iter().enumerate() {
+            if i == 5 {
+                assert_eq!(result, VerificationResult::ChallengeVerificationFailed);
+            } else {
+                assert_eq!(result, VerificationResult::ChallengeVerifiedSuccess);
+            }
+        }
+    }
+
+    #[test]
+    fn test_verify_batch_ristretto() {
+        let material = RistrettoMaterial::default();
+        let mut verifications: Vec<_> = (0..32).map(|_| proof(&material)).collect();
+        verifications[20].ys[1] = verifications[21].ys[1];
+
+        let results = verify_batch(&verifications);
+        for (i, result) in results.into_iter().enumerate() {
+            if i == 20 {
+                assert_eq!(result, VerificationResult::ChallengeVerificationFailed);
+            } else {
+                assert_eq!(result, VerificationResult::ChallengeVerifiedSuccess);
+            }
+        }
+    }
+}
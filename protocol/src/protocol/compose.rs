@@ -0,0 +1,518 @@
+//! This module contains the AND and OR compositions of sigma protocols.
+//!
+//! - `And`: Proves knowledge of the witnesses of all the statements. Every branch is run with the same challenge, so
+//!   the whole conjunction takes a single exchange. This is what a multi-key account uses.
+//! - `Or`: Proves knowledge of the witness of one of the statements, without revealing which one, following Cramer,
+//!   Damgård and Schoenmakers. The prover simulates every branch it does not know the witness of with a challenge it
+//!   picks in advance, and answers the branch it knows with the rest of the challenge of the verifier: the branch
+//!   challenges must add up to it. This is what an anonymous "one of my team's keys" login uses.
+//!
+//! Both compositions are themselves `SigmaProtocol`s over the same group as their branches, so they can be nested,
+//! e.g. to prove knowledge of one of several groups of keys. Their constructors refuse branches whose material has
+//! different fingerprints.
+use crate::protocol::cp::VerificationResult;
+use crate::protocol::error::{ProtocolError, RejectionReason};
+use crate::protocol::group::Group;
+use crate::protocol::sigma::{SigmaProtocol, SigmaScalar};
+use crate::protocol::simulator;
+use rand::{CryptoRng, RngCore};
+
+/// Conjunction of sigma protocols over the same group.
+#[derive(Debug, Clone)]
+pub struct And<S: SigmaProtocol> {
+    pub protocols: Vec<S>,
+}
+
+impl<S: SigmaProtocol> And<S> {
+    /// Creates the conjunction of the given protocols, one per statement.
+    ///
+    /// # Returns
+    ///
+    /// The conjunction, or `ProtocolError::InvalidParameter` if there are no protocols or they are not all over the
+    /// same group.
+    pub fn new(protocols: Vec<S>) -> Result<Self, ProtocolError> {
+        if protocols.is_empty() {
+            return Err(ProtocolError::InvalidParameter(
+                "a conjunction needs at least one protocol",
+            ));
+        }
+        check_same_group(&protocols)?;
+        Ok(And { protocols })
+    }
+}
+
+impl<S: SigmaProtocol> SigmaProtocol for And<S> {
+    type Group = S::Group;
+    /// The statements of all the branches.
+    type Statement = Vec<S::Statement>;
+    /// The witnesses of all the branches.
+    type Witness = Vec<S::Witness>;
+    type Commitment = Vec<S::Commitment>;
+    type Nonce = Vec<S::Nonce>;
+    type Response = Vec<S::Response>;
+
+    fn material(&self) -> &S::Group {
+        self.protocols[0].material()
+    }
+
+    /// # Panics
+    ///
+    /// Panics if there is not one statement and one witness per protocol.
+    fn commit<R: RngCore + CryptoRng>(
+        &self,
+        statements: &Vec<S::Statement>,
+        witnesses: &Vec<S::Witness>,
+        rng: &mut R,
+    ) -> (Vec<S::Commitment>, Vec<S::Nonce>) {
+        assert_eq!(
+            statements.len(),
+            self.protocols.len(),
+            "one statement per protocol"
+        );
+        assert_eq!(
+            witnesses.len(),
+            self.protocols.len(),
+            "one witness per protocol"
+        );
+        self.protocols
+            .iter()
+            .zip(statements.iter().zip(witnesses))
+            .map(|(protocol, (statement, witness))| protocol.commit(statement, witness, rng))
+            .unzip()
+    }
+
+    fn respond(
+        &self,
+        witnesses: &Vec<S::Witness>,
+        nonces: Vec<S::Nonce>,
+        c: &SigmaScalar<S>,
+    ) -> Vec<S::Response> {
+        self.protocols
+            .iter()
+            .zip(witnesses.iter().zip(nonces))
+            .map(|(protocol, (witness, nonce))| protocol.respond(witness, nonce, c))
+            .collect()
+    }
+
+    /// Verifies every branch with the same challenge.
+    fn verify(
+        &self,
+        statements: &Vec<S::Statement>,
+        commitments: &Vec<S::Commitment>,
+        c: &SigmaScalar<S>,
+        responses: &Vec<S::Response>,
+    ) -> VerificationResult {
+        if let Err(reason) = check_lengths(
+            self.protocols.len(),
+            &[
+                ("statements", statements.len()),
+                ("commitments", commitments.len()),
+                ("responses", responses.len()),
+            ],
+        ) {
+            return VerificationResult::ChallengeRejected(reason);
+        }
+        combine(
+            self.protocols
+                .iter()
+                .zip(statements)
+                .zip(commitments.iter().zip(responses))
+                .map(|((protocol, statement), (commitment, response))| {
+                    protocol.verify(statement, commitment, c, response)
+                }),
+        )
+    }
+
+    fn simulate<R: RngCore + CryptoRng>(
+        &self,
+        statements: &Vec<S::Statement>,
+        c: &SigmaScalar<S>,
+        rng: &mut R,
+    ) -> (Vec<S::Commitment>, Vec<S::Response>) {
+        self.protocols
+            .iter()
+            .zip(statements)
+            .map(|(protocol, statement)| protocol.simulate(statement, c, rng))
+            .unzip()
+    }
+}
+
+/// Disjunction of sigma protocols over the same group, as in Cramer-Damgård-Schoenmakers.
+#[derive(Debug, Clone)]
+pub struct Or<S: SigmaProtocol> {
+    pub protocols: Vec<S>,
+}
+
+impl<S: SigmaProtocol> Or<S> {
+    /// Creates the disjunction of the given protocols, one per statement.
+    ///
+    /// # Returns
+    ///
+    /// The disjunction, or `ProtocolError::InvalidParameter` if there are no protocols or they are not all over the
+    /// same group.
+    pub fn new(protocols: Vec<S>) -> Result<Self, ProtocolError> {
+        if protocols.is_empty() {
+            return Err(ProtocolError::InvalidParameter(
+                "a disjunction needs at least one protocol",
+            ));
+        }
+        check_same_group(&protocols)?;
+        Ok(Or { protocols })
+    }
+}
+
+/// Witness of an OR-proof: the witness of one of the statements. It does not implement `Debug`, so neither the
+/// witness nor the index it reveals end up in logs.
+pub struct OrWitness<W> {
+    /// The index of the statement the witness is for.
+    pub index: usize,
+    pub witness: W,
+}
+
+/// Secret state of the prover of an OR-proof between `commit` and `respond`.
+pub struct OrNonce<S: SigmaProtocol> {
+    /// The nonce of the branch the prover knows the witness of.
+    nonce: S::Nonce,
+    /// The challenges of the simulated branches, and a placeholder for the known one.
+    challenges: Vec<SigmaScalar<S>>,
+    /// The responses of the simulated branches, and `None` for the known one.
+    responses: Vec<Option<S::Response>>,
+}
+
+/// Response of an OR-proof: the challenge and the response of every branch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrResponse<C, R> {
+    /// The challenges of the branches, which add up to the challenge of the verifier.
+    pub challenges: Vec<C>,
+    pub responses: Vec<R>,
+}
+
+impl<S: SigmaProtocol> SigmaProtocol for Or<S> {
+    type Group = S::Group;
+    /// The statements of all the branches.
+    type Statement = Vec<S::Statement>;
+    type Witness = OrWitness<S::Witness>;
+    type Commitment = Vec<S::Commitment>;
+    type Nonce = OrNonce<S>;
+    type Response = OrResponse<SigmaScalar<S>, S::Response>;
+
+    fn material(&self) -> &S::Group {
+        self.protocols[0].material()
+    }
+
+    /// Commits to the branch of the witness, and simulates every other branch with a uniformly random challenge.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is not one statement per protocol, or the index of the witness is out of range.
+    fn commit<R: RngCore + CryptoRng>(
+        &self,
+        statements: &Vec<S::Statement>,
+        witness: &OrWitness<S::Witness>,
+        rng: &mut R,
+    ) -> (Vec<S::Commitment>, OrNonce<S>) {
+        assert_eq!(
+            statements.len(),
+            self.protocols.len(),
+            "one statement per protocol"
+        );
+        assert!(
+            witness.index < self.protocols.len(),
+            "the witness index is out of range"
+        );
+        let material = self.material();
+        let mut commitments = Vec::with_capacity(self.protocols.len());
+        let mut challenges = Vec::with_capacity(self.protocols.len());
+        let mut responses = Vec::with_capacity(self.protocols.len());
+        let mut known = None;
+        for (i, (protocol, statement)) in self.protocols.iter().zip(statements).enumerate() {
+            if i == witness.index {
+                let (commitment, nonce) = protocol.commit(statement, &witness.witness, rng);
+                commitments.push(commitment);
+                challenges.push(material.scalar_from_u128(0));
+                responses.push(None);
+                known = Some(nonce);
+            } else {
+                let c = simulator::uniform_scalar(material, rng);
+                let (commitment, response) = protocol.simulate(statement, &c, rng);
+                commitments.push(commitment);
+                challenges.push(c);
+                responses.push(Some(response));
+            }
+        }
+        let nonce = OrNonce {
+            nonce: known.expect("the witness index is in range"),
+            challenges,
+            responses,
+        };
+        (commitments, nonce)
+    }
+
+    /// Answers the known branch with the challenge of the verifier minus the challenges of the simulated ones.
+    fn respond(
+        &self,
+        witness: &OrWitness<S::Witness>,
+        nonce: OrNonce<S>,
+        c: &SigmaScalar<S>,
+    ) -> OrResponse<SigmaScalar<S>, S::Response> {
+        let material = self.material();
+        let OrNonce {
+            nonce,
+            mut challenges,
+            responses,
+        } = nonce;
+        // The placeholder challenge of the known branch is zero.
+        let c_known = material.scalar_sub(c, &sum(material, &challenges));
+        let response = self.protocols[witness.index].respond(&witness.witness, nonce, &c_known);
+        challenges[witness.index] = c_known;
+        let mut response = Some(response);
+        let responses = responses
+            .into_iter()
+            .map(|r| {
+                r.or_else(|| response.take())
+                    .expect("one branch is not simulated")
+            })
+            .collect();
+        OrResponse {
+            challenges,
+            responses,
+        }
+    }
+
+    /// Checks that the challenges of the branches add up to `c`, and verifies every branch with its own challenge.
+    fn verify(
+        &self,
+        statements: &Vec<S::Statement>,
+        commitments: &Vec<S::Commitment>,
+        c: &SigmaScalar<S>,
+        response: &OrResponse<SigmaScalar<S>, S::Response>,
+    ) -> VerificationResult {
+        let material = self.material();
+        if let Err(reason) = check_lengths(
+            self.protocols.len(),
+            &[
+                ("statements", statements.len()),
+                ("commitments", commitments.len()),
+                ("challenges", response.challenges.len()),
+                ("responses", response.responses.len()),
+            ],
+        ) {
+            return VerificationResult::ChallengeRejected(reason);
+        }
+        if response
+            .challenges
+            .iter()
+            .any(|c_i| !material.is_scalar(c_i))
+        {
+            return VerificationResult::ChallengeRejected(RejectionReason::OutOfRange(
+                "challenges",
+            ));
+        }
+        if &sum(material, &response.challenges) != c {
+            tracing::info!("OR-proof challenges do not add up to the challenge");
+            return VerificationResult::ChallengeVerificationFailed;
+        }
+        combine(
+            self.protocols
+                .iter()
+                .zip(statements.iter().zip(commitments))
+                .zip(response.challenges.iter().zip(&response.responses))
+                .map(|((protocol, (statement, commitment)), (c_i, response))| {
+                    protocol.verify(statement, commitment, c_i, response)
+                }),
+        )
+    }
+
+    /// Simulates every branch, with uniformly random challenges adding up to `c`.
+    fn simulate<R: RngCore + CryptoRng>(
+        &self,
+        statements: &Vec<S::Statement>,
+        c: &SigmaScalar<S>,
+        rng: &mut R,
+    ) -> (Vec<S::Commitment>, OrResponse<SigmaScalar<S>, S::Response>) {
+        let material = self.material();
+        let n = self.protocols.len();
+        let mut challenges: Vec<_> = (1..n)
+            .map(|_| simulator::uniform_scalar(material, rng))
+            .collect();
+        let last = material.scalar_sub(c, &sum(material, &challenges));
+        challenges.push(last);
+        let (commitments, responses) = self
+            .protocols
+            .iter()
+            .zip(statements)
+            .zip(&challenges)
+            .map(|((protocol, statement), c_i)| protocol.simulate(statement, c_i, rng))
+            .unzip();
+        (
+            commitments,
+            OrResponse {
+                challenges,
+                responses,
+            },
+        )
+    }
+}
+
+/// Adds up the scalars.
+fn sum<G: Group>(material: &G, scalars: &[G::Scalar]) -> G::Scalar {
+    scalars.iter().fold(material.scalar_from_u128(0), |sum, s| {
+        material.scalar_add(&sum, s)
+    })
+}
+
+/// Checks that all the protocols are over the same group, by comparing the fingerprints of their material. The
+/// challenge is shared or split between the branches, which is only sound if all of them reduce it modulo the same
+/// order.
+fn check_same_group<S: SigmaProtocol>(protocols: &[S]) -> Result<(), ProtocolError> {
+    let fingerprint = protocols[0].material().fingerprint();
+    if protocols
+        .iter()
+        .any(|protocol| protocol.material().fingerprint() != fingerprint)
+    {
+        return Err(ProtocolError::InvalidParameter(
+            "all the protocols must be over the same group",
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that every received list has one value per branch.
+fn check_lengths(
+    branches: usize,
+    lengths: &[(&'static str, usize)],
+) -> Result<(), RejectionReason> {
+    match lengths.iter().find(|(_, len)| *len != branches) {
+        Some((name, _)) => Err(RejectionReason::WrongLength(name)),
+        None => Ok(()),
+    }
+}
+
+/// Combines the results of the branches: the first rejection if any, otherwise success only if all of them succeed.
+fn combine(results: impl Iterator<Item = VerificationResult>) -> VerificationResult {
+    let mut combined = VerificationResult::ChallengeVerifiedSuccess;
+    for result in results {
+        match result {
+            VerificationResult::ChallengeRejected(_) => return result,
+            VerificationResult::ChallengeVerificationFailed => combined = result,
+            VerificationResult::ChallengeVerifiedSuccess => {}
+        }
+    }
+    combined
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::cp::{ChaumPedersen, ChaumPedersenStatement, Material};
+    use crate::protocol::ristretto::RistrettoMaterial;
+    use crate::protocol::schnorr::Schnorr;
+    use crate::protocol::test_support::run;
+    use num_bigint::BigInt;
+
+    fn team(material: &Material) -> (Vec<ChaumPedersenStatement>, Vec<BigInt>) {
+        let keys: Vec<BigInt> = [11, 22, 33].map(BigInt::from).into();
+        let statements = keys
+            .iter()
+            .map(|x| ChaumPedersenStatement::new(material, x))
+            .collect();
+        (statements, keys)
+    }
+
+    #[test]
+    fn test_and() {
+        let material = Material::generate(256, None).unwrap();
+        let (statements, keys) = team(&material);
+        let and = And::new(vec![ChaumPedersen::new(material.clone()); 3]).unwrap();
+        assert_eq!(
+            run(&and, &statements, &keys),
+            VerificationResult::ChallengeVerifiedSuccess
+        );
+        let wrong = vec![keys[0].clone(), keys[1].clone(), keys[1].clone()];
+        assert_eq!(
+            run(&and, &statements, &wrong),
+            VerificationResult::ChallengeVerificationFailed
+        );
+
+        let mut rng = rand::thread_rng();
+        let c = and.challenge(&mut rng);
+        let (commitments, responses) = and.simulate(&statements, &c, &mut rng);
+        assert_eq!(
+            and.verify(&statements, &commitments, &c, &responses),
+            VerificationResult::ChallengeVerifiedSuccess
+        );
+        assert_eq!(
+            and.verify(&statements[..2].to_vec(), &commitments, &c, &responses),
+            VerificationResult::ChallengeRejected(RejectionReason::WrongLength("statements"))
+        );
+    }
+
+    #[test]
+    fn test_or() {
+        let material = Material::generate(256, None).unwrap();
+        let (statements, keys) = team(&material);
+        let or = Or::new(vec![ChaumPedersen::new(material.clone()); 3]).unwrap();
+        for (index, witness) in keys.into_iter().enumerate() {
+            let witness = OrWitness { index, witness };
+            assert_eq!(
+                run(&or, &statements, &witness),
+                VerificationResult::ChallengeVerifiedSuccess
+            );
+        }
+        let outsider = OrWitness {
+            index: 1,
+            witness: BigInt::from(44),
+        };
+        assert_eq!(
+            run(&or, &statements, &outsider),
+            VerificationResult::ChallengeVerificationFailed
+        );
+
+        let mut rng = rand::thread_rng();
+        let c = or.challenge(&mut rng);
+        let (commitments, mut response) = or.simulate(&statements, &c, &mut rng);
+        assert_eq!(
+            or.verify(&statements, &commitments, &c, &response),
+            VerificationResult::ChallengeVerifiedSuccess
+        );
+        // The simulated branches of a prover must not be able to pick all the challenges.
+        response.challenges[0] = material.scalar_add(&response.challenges[0], &1.into());
+        assert_eq!(
+            or.verify(&statements, &commitments, &c, &response),
+            VerificationResult::ChallengeVerificationFailed
+        );
+    }
+
+    #[test]
+    fn test_different_groups() {
+        let protocols = vec![
+            Schnorr::new(Material::default()),
+            Schnorr::new(Material::generate(64, None).unwrap()),
+        ];
+        let error = Err(ProtocolError::InvalidParameter(
+            "all the protocols must be over the same group",
+        ));
+        assert_eq!(And::new(protocols.clone()).map(|_| ()), error);
+        assert_eq!(Or::new(protocols).map(|_| ()), error);
+    }
+
+    #[test]
+    fn test_nested() {
+        let material = RistrettoMaterial::default();
+        let mut rng = rand::thread_rng();
+        let keys: Vec<_> = (0..4).map(|_| material.random_scalar(&mut rng)).collect();
+        let statements: Vec<_> = keys.iter().map(|x| material.exp(material.g(), x)).collect();
+        // Knowledge of both keys of one of two pairs.
+        let pair = And::new(vec![Schnorr::new(material.clone()); 2]).unwrap();
+        let or = Or::new(vec![pair.clone(), pair]).unwrap();
+        let statements = vec![statements[..2].to_vec(), statements[2..].to_vec()];
+        let witness = OrWitness {
+            index: 1,
+            witness: keys[2..].to_vec(),
+        };
+        assert_eq!(
+            run(&or, &statements, &witness),
+            VerificationResult::ChallengeVerifiedSuccess
+        );
+    }
+}
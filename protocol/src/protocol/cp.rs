@@ -1,13 +1,13 @@
 //! This module contains the implementation of a Chaum-Pedersen ZK protocol for registration, commitment, challenge, and verification.
 //!
 //! The protocol is defined by a series of steps, each represented by a corresponding struct. The steps include:
-//! - `Register`: Represents the registration step, where a user registers with a material and generates `y1` and `y2` values.
-//! - `Commitment`: Represents the commitment step, where the user generates commitment values `r1` and `r2` based on the material and a random value `k`.
+//! - `Register`: Represents the registration step, where a user registers with a material and generates the values `y_i = b_i^x` of a list of bases, `y1 = g^x` and `y2 = h^x` by default.
+//! - `Commitment`: Represents the commitment step, where the user generates the commitment values `r_i = b_i^k` (`r1` and `r2` by default) based on the material and a random value `k`.
 //! - `Challenge`: Represents the challenge step, where a challenge value `c` is generated.
 //! - `ChallengeResponse`: Represents the challenge response step, where the user calculates a response `s` based on the challenge, material, and private key `x`.
 //! - `VerificationRequest`: Represents the verification request step, where the user sends a verification request with the authentication ID and response `s`.
 //! - `Verification`: Represents the verification step, where the server verifies the response `s` based on the received values and material.
-//! - `VerificationResult`: Represents the result of the verification step, indicating whether the challenge was successfully verified or not.
+//! - `VerificationResult`: Represents the result of the verification step, indicating whether the challenge was successfully verified, failed, or was rejected because the received values are malformed.
 //!
 //! The protocol steps are implemented as structs with associated methods for generating the next step based on the current state. The protocol steps also implement the `ProtocolStep` trait, which allows them to be used generically in the `ProtocolState` struct.
 //!
@@ -15,27 +15,90 @@
 //!
 //! The protocol transitions are defined by the `ProtocolTransition` trait, which provides a `change` method to transition to the next step. Each step implements the `ProtocolTransition` trait for the corresponding next step.
 //!
+//! The cryptography of the steps is implemented by `dleq`, the proof of equality of discrete logarithms over any number of bases, which the transitions delegate to. With the default bases `g` and `h` the steps run the Chaum-Pedersen proof of `ChaumPedersen`.
+//!
+//! All steps are generic over the `Group` the protocol runs on, and default to `Material`, the subgroup of order `q` of the integers modulo a safe prime `p`.
+//!
 //! The module also includes unit tests for the protocol transitions, ensuring that the protocol progresses correctly from one step to another.
-use num_bigint::{BigInt, RandBigInt};
-use num_primes::Generator;
-use num_traits::{One, ToPrimitive};
+use crate::protocol::ct;
+use crate::protocol::dleq::{self, Dleq};
+use crate::protocol::encoding;
+use crate::protocol::error::{MaterialError, ProtocolError, RejectionReason};
+use crate::protocol::group::Group;
+use crate::protocol::groups::NamedGroup;
+use crate::protocol::multiexp::{self, FixedBaseCache, FixedBaseTable};
+use crate::protocol::nonce;
+use crate::protocol::primes;
+use crate::protocol::secret::Secret;
+use crate::protocol::sigma::SigmaProtocol;
+use crate::protocol::simulator;
+use crate::protocol::transcript::Transcript;
+use num_bigint::{BigInt, RandBigInt, Sign};
+use num_traits::One;
+use rand::{CryptoRng, Rng, RngCore};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
 use std::ops::Deref;
 use typed_builder::TypedBuilder;
-use uuid::Uuid;
+use uuid::Builder;
 
 #[derive(Debug, Clone, TypedBuilder)]
-pub struct Register {
-    pub material: Material,
-    pub y1: BigInt,
-    pub y2: BigInt,
+pub struct Register<G: Group = Material> {
+    pub material: G,
+    /// The bases `b_i`, by default `g` and `h`.
+    #[builder(default_code = "vec![material.g().clone(), material.h().clone()]")]
+    pub bases: Vec<G::Element>,
+    /// The public values `y_i = b_i^x`, one per base.
+    pub ys: Vec<G::Element>,
 }
 
-impl Register {
-    pub fn new(material: Material, x: &BigInt) -> Self {
-        let y1 = material.g.modpow(x, &material.p);
-        let y2 = material.h.modpow(x, &material.p);
-        Register { material, y1, y2 }
+impl<G: Group> Register<G> {
+    /// Registers the secret `x` for the bases `g` and `h`, so that `y1 = g^x` and `y2 = h^x`.
+    pub fn new(material: G, x: &G::Scalar) -> Self {
+        let ChaumPedersenStatement { y1, y2 } = ChaumPedersenStatement::new(&material, x);
+        Register {
+            bases: vec![material.g().clone(), material.h().clone()],
+            material,
+            ys: vec![y1, y2],
+        }
+    }
+
+    /// Registers the secret `x` for any list of bases, so that `y_i = b_i^x`.
+    ///
+    /// # Returns
+    ///
+    /// The registration, or `ProtocolError::InvalidParameter` if there are no bases or any of them is not a
+    /// non-trivial element of the group.
+    pub fn with_bases(
+        material: G,
+        bases: Vec<G::Element>,
+        x: &G::Scalar,
+    ) -> Result<Self, ProtocolError> {
+        let Dleq { material, bases } = Dleq::new(material, bases)?;
+        let ys = dleq::commit(&material, &bases, x);
+        Ok(Register {
+            material,
+            bases,
+            ys,
+        })
+    }
+
+    /// Returns `y1`, the public value of the first base.
+    ///
+    /// # Panics
+    ///
+    /// If there are no bases.
+    pub fn y1(&self) -> &G::Element {
+        &self.ys[0]
+    }
+
+    /// Returns `y2`, the public value of the second base.
+    ///
+    /// # Panics
+    ///
+    /// If there are less than two bases.
+    pub fn y2(&self) -> &G::Element {
+        &self.ys[1]
     }
 }
 
@@ -62,7 +125,12 @@ impl Deref for AuthId {
     }
 }
 
+/// The parameters of a safe-prime group.
+///
+/// `Material::builder` checks the moduli with `Material::check_moduli`, so its `build` returns a `Result`. The other
+/// checks of `Material::validate` are left to the caller.
 #[derive(Debug, Clone, TypedBuilder)]
+#[builder(build_method(into = Result<Material, MaterialError>))]
 pub struct Material {
     #[builder(setter(into))]
     pub g: BigInt,
@@ -72,119 +140,726 @@ pub struct Material {
     pub q: BigInt,
     #[builder(setter(into))]
     pub p: BigInt,
+    /// The well-known group the material was built from, if any.
+    #[builder(default, setter(strip_option))]
+    pub group: Option<NamedGroup>,
+    /// The public seed `h` was derived from, if any. See `Material::derive_h`.
+    #[builder(default, setter(strip_option, into))]
+    pub h_seed: Option<String>,
+    /// Fixed-base tables of `g` and `h`, built on first use and shared between clones.
+    #[builder(default, setter(skip))]
+    tables: FixedBaseCache,
 }
 
+/// Default bit length of the safe prime `p`.
+pub const DEFAULT_SECURITY_BITS: usize = 2048;
+
+/// Minimum bit length of a generated safe prime `p`.
+pub const MIN_BITS: usize = 8;
+
+/// Default public seed used to derive `h`.
+pub const DEFAULT_H_SEED: &str = "zk-cp-protocol";
+
+/// Domain separation tag for the derivation of `h`.
+const H_DERIVATION_DST: &[u8] = b"zk-cp-protocol/derive-h/sha256/v1";
+
 impl Material {
-    pub fn generate(g: Option<BigInt>) -> Self {
-        let p_prime = Generator::safe_prime(16);
-        let p: BigInt = p_prime.to_u64().unwrap().into();
-        let q: BigInt = (p.clone() - BigInt::one()) / 2;
-        let g: BigInt = g.unwrap_or(7.into());
-        let h: BigInt = g.modpow(&BigInt::from(11), &p);
-        Material { g, h, p, q }
+    /// Generates new material over a random safe prime `p` of `bits` bits.
+    ///
+    /// The given base (or `7` if none is given) is squared modulo `p`, so that `g` lies in the subgroup of quadratic
+    /// residues, which has prime order `q = (p - 1) / 2`.
+    ///
+    /// # Arguments
+    ///
+    /// * `bits` - The bit length of `p`, for example `2048` or `3072`.
+    /// * `g` - An optional base for the generator `g`.
+    ///
+    /// # Returns
+    ///
+    /// The material, or `ProtocolError::UnsupportedBits` if `bits` is not between `MIN_BITS` and `ct::MAX_BITS`, or
+    /// `MaterialError::InvalidGenerator` if the square of the base is `1` modulo `p`.
+    pub fn generate(bits: usize, g: Option<BigInt>) -> Result<Self, ProtocolError> {
+        if !(MIN_BITS..=ct::MAX_BITS).contains(&bits) {
+            return Err(ProtocolError::UnsupportedBits {
+                min: MIN_BITS,
+                max: ct::MAX_BITS,
+                actual: bits,
+            });
+        }
+        let p = primes::generate_safe_prime(bits);
+        let q: BigInt = (&p - BigInt::one()) / 2;
+        let g: BigInt = g.unwrap_or(7.into()).modpow(&BigInt::from(2), &p);
+        if g.is_one() || g.sign() != Sign::Plus {
+            return Err(MaterialError::InvalidGenerator("g").into());
+        }
+        let h: BigInt = Material::derive_h(&p, &g, DEFAULT_H_SEED);
+        Ok(Material {
+            g,
+            h,
+            p,
+            q,
+            group: None,
+            h_seed: Some(DEFAULT_H_SEED.to_string()),
+            tables: FixedBaseCache::default(),
+        })
+    }
+
+    /// Builds the material from one of the well-known RFC 3526 or RFC 7919 groups.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - The named group providing `p`, `q` and `g`.
+    pub fn from_named_group(group: NamedGroup) -> Self {
+        let p = group.p();
+        let g = group.g();
+        let h = Material::derive_h(&p, &g, DEFAULT_H_SEED);
+        Material {
+            g,
+            h,
+            q: group.q(),
+            p,
+            group: Some(group),
+            h_seed: Some(DEFAULT_H_SEED.to_string()),
+            tables: FixedBaseCache::default(),
+        }
+    }
+
+    /// Replaces `h` with the one derived from the given public seed.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The public seed to derive `h` from.
+    pub fn with_h_seed(self, seed: &str) -> Self {
+        Material {
+            h: Material::derive_h(&self.p, &self.g, seed),
+            h_seed: Some(seed.to_string()),
+            tables: FixedBaseCache::default(),
+            ..self
+        }
+    }
+
+    /// Derives a generator `h` of the subgroup of order `q` from a public seed, in such a way that nobody knows
+    /// `log_g(h)`.
+    ///
+    /// The seed, together with `p` and `g`, is expanded with SHA-256 into 128 bits more than the size of `p`, the
+    /// result is reduced modulo `p` and squared, which maps it into the subgroup of quadratic residues. A counter is
+    /// increased until the result is neither `1` nor `g`.
+    ///
+    /// # Arguments
+    ///
+    /// * `p` - The safe prime of the group.
+    /// * `g` - The generator `g`, which is bound into the derivation.
+    /// * `seed` - The public seed.
+    pub fn derive_h(p: &BigInt, g: &BigInt, seed: &str) -> BigInt {
+        let len = (p.bits() as usize).div_ceil(8) + 16;
+        let (_, p_bytes) = p.to_bytes_be();
+        let (_, g_bytes) = g.to_bytes_be();
+        for counter in 0u32.. {
+            let mut bytes = Vec::with_capacity(len + 32);
+            for block in 0u32.. {
+                if bytes.len() >= len {
+                    break;
+                }
+                let digest = Sha256::new()
+                    .chain_update(H_DERIVATION_DST)
+                    .chain_update((p_bytes.len() as u32).to_be_bytes())
+                    .chain_update(&p_bytes)
+                    .chain_update((g_bytes.len() as u32).to_be_bytes())
+                    .chain_update(&g_bytes)
+                    .chain_update((seed.len() as u32).to_be_bytes())
+                    .chain_update(seed.as_bytes())
+                    .chain_update(counter.to_be_bytes())
+                    .chain_update(block.to_be_bytes())
+                    .finalize();
+                bytes.extend_from_slice(&digest);
+            }
+            let h = BigInt::from_bytes_be(Sign::Plus, &bytes[..len]).modpow(&BigInt::from(2), p);
+            if !h.is_one() && &h != g {
+                return h;
+            }
+        }
+        unreachable!("the counter space is never exhausted")
+    }
+
+    /// Validates the group parameters.
+    ///
+    /// Checks that `p` has at most `ct::MAX_BITS` bits and is a safe prime, that is `q = (p - 1) / 2` and both `p` and `q` are prime, and that `g` and `h`
+    /// are different non-trivial elements of the subgroup of order `q`. The primality checks are skipped when `p` and
+    /// `q` are the ones of the named group the material claims to be built from.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which is `Ok(())` if the material is valid, or the first `MaterialError` found otherwise.
+    pub fn validate(&self) -> Result<(), MaterialError> {
+        self.check_moduli()?;
+        let one = BigInt::one();
+        if self.q <= one {
+            return Err(MaterialError::QMismatch);
+        }
+        let vetted = self
+            .group
+            .is_some_and(|group| group.p() == self.p && group.q() == self.q);
+        if !vetted {
+            if !primes::is_probable_prime(&self.q) {
+                return Err(MaterialError::QNotPrime);
+            }
+            if !primes::is_probable_prime(&self.p) {
+                return Err(MaterialError::PNotPrime);
+            }
+        }
+        for (name, e) in [("g", &self.g), ("h", &self.h)] {
+            if e <= &one || e >= &self.p || !e.modpow(&self.q, &self.p).is_one() {
+                return Err(MaterialError::InvalidGenerator(name));
+            }
+        }
+        if self.g == self.h {
+            return Err(MaterialError::EqualGenerators);
+        }
+        Ok(())
+    }
+
+    /// Checks that `p` and `q` can be used by the constant-time arithmetic of `ct`, which panics otherwise.
+    ///
+    /// Unlike `Material::validate`, this check is cheap: `p` must have at most `ct::MAX_BITS` bits, `q` must be
+    /// `(p - 1) / 2`, and both must be positive odd numbers. It runs whenever material is built from untrusted input,
+    /// by `Material::builder`, `MaterialSerde::to_material` and `encoding::decode_material`, so malformed material is
+    /// an error before any secret exponentiation.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which is `Ok(())` if the moduli are supported, or the first `MaterialError` found otherwise.
+    pub fn check_moduli(&self) -> Result<(), MaterialError> {
+        if self.p.bits() > ct::MAX_BITS as u64 {
+            return Err(MaterialError::PTooLarge);
+        }
+        if self.q != (&self.p - BigInt::one()) / 2 {
+            return Err(MaterialError::QMismatch);
+        }
+        for (name, n) in [("p", &self.p), ("q", &self.q)] {
+            if n.sign() != Sign::Plus || !n.bit(0) {
+                return Err(MaterialError::InvalidModulus(name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that `h` is the one derived from the recorded public seed.
+    ///
+    /// Returns `false` if there is no seed, since in that case the discrete logarithm of `h` relative to `g` might be
+    /// known.
+    pub fn verify_h(&self) -> bool {
+        self.h_seed
+            .as_ref()
+            .is_some_and(|seed| Material::derive_h(&self.p, &self.g, seed) == self.h)
+    }
+}
+
+impl Material {
+    /// Builds the fixed-base tables of `g` and `h`, unless they were already built for this material or any of its
+    /// clones.
+    ///
+    /// The tables are otherwise built on the first exponentiation of `g` or `h`. Calling this method when the material
+    /// is loaded moves that cost out of the first protocol run.
+    pub fn precompute(&self) -> &[FixedBaseTable; 2] {
+        self.tables.get_or_init(|| {
+            let bits = self.q.bits();
+            [
+                FixedBaseTable::new(&self.g, &self.p, bits),
+                FixedBaseTable::new(&self.h, &self.p, bits),
+            ]
+        })
+    }
+
+    /// Returns the fixed-base table of `base`, if it is `g` or `h`.
+    fn fixed_base(&self, base: &BigInt) -> Option<&FixedBaseTable> {
+        if base != &self.g && base != &self.h {
+            return None;
+        }
+        self.precompute()
+            .iter()
+            .find(|table| table.is_for(base, &self.p))
+    }
+
+    /// Reduces `n` modulo `modulus`, unless it is already in the range `[0, modulus)`.
+    ///
+    /// Values built by the protocol are always reduced, so the variable-time reduction only runs for values given by
+    /// the caller, such as a secret `x` larger than `q`. The result is a copy of `n`, which is not wiped when it is
+    /// dropped, see the `secret` module.
+    fn reduce(&self, n: &BigInt, modulus: &BigInt) -> BigInt {
+        if n.sign() != Sign::Minus && n < modulus {
+            n.clone()
+        } else {
+            ((n % modulus) + modulus) % modulus
+        }
+    }
+
+    /// Number of bytes of the fixed-length encoding of an element.
+    fn element_len(&self) -> usize {
+        (self.p.bits() as usize).div_ceil(8)
+    }
+
+    /// Number of bytes of the fixed-length encoding of a scalar.
+    fn scalar_len(&self) -> usize {
+        (self.q.bits() as usize).div_ceil(8)
+    }
+}
+
+/// Encodes bytes as lowercase hexadecimal.
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Decodes hexadecimal bytes, or returns `None` if the string has an odd length or any other character.
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Encodes a non-negative integer as a big-endian byte string of exactly `len` bytes.
+fn to_fixed_bytes(n: &BigInt, len: usize) -> Vec<u8> {
+    let (_, bytes) = n.to_bytes_be();
+    let mut fixed = vec![0; len.saturating_sub(bytes.len())];
+    fixed.extend_from_slice(&bytes);
+    fixed
+}
+
+impl Group for Material {
+    type Element = BigInt;
+    type Scalar = BigInt;
+
+    fn g(&self) -> &BigInt {
+        &self.g
+    }
+
+    fn h(&self) -> &BigInt {
+        &self.h
+    }
+
+    fn identity(&self) -> BigInt {
+        BigInt::one()
+    }
+
+    /// Uses the fixed-base tables for `g` and `h`, and `BigInt::modpow` for any other base.
+    fn exp(&self, base: &BigInt, exponent: &BigInt) -> BigInt {
+        self.fixed_base(base)
+            .and_then(|table| table.exp(exponent))
+            .unwrap_or_else(|| base.modpow(exponent, &self.p))
+    }
+
+    fn mul(&self, a: &BigInt, b: &BigInt) -> BigInt {
+        (a * b) % &self.p
+    }
+
+    /// Runs on fixed-width integers with `ct::modpow`, and never uses the fixed-base tables, whose lookups depend on
+    /// the exponent.
+    fn exp_secret(&self, base: &BigInt, exponent: &BigInt) -> BigInt {
+        ct::modpow(
+            &self.reduce(base, &self.p),
+            &self.reduce(exponent, &self.q),
+            &self.p,
+        )
+    }
+
+    fn element_ct_eq(&self, a: &BigInt, b: &BigInt) -> bool {
+        ct::eq(a, b, self.element_len())
+    }
+
+    /// For a safe prime `p = 2q + 1` the subgroup of order `q` is the subgroup of quadratic residues, so membership is
+    /// checked with the Legendre symbol, which is much cheaper than computing `e^q`.
+    fn is_element(&self, e: &BigInt) -> bool {
+        if e <= &BigInt::one() || e >= &self.p {
+            return false;
+        }
+        if self.p.bit(0) && self.q == (&self.p - 1u32) / 2u32 {
+            primes::jacobi(e, &self.p) == 1
+        } else {
+            e.modpow(&self.q, &self.p).is_one()
+        }
+    }
+
+    fn is_scalar(&self, s: &BigInt) -> bool {
+        s.sign() != Sign::Minus && s < &self.q
+    }
+
+    fn random_scalar<R: RngCore + CryptoRng>(&self, rng: &mut R) -> BigInt {
+        rng.gen_bigint_range(&BigInt::one(), &self.q)
+    }
+
+    fn scalar_add(&self, a: &BigInt, b: &BigInt) -> BigInt {
+        (a + b) % &self.q
+    }
+
+    fn scalar_sub(&self, a: &BigInt, b: &BigInt) -> BigInt {
+        let d = (a - b) % &self.q;
+        if d.sign() == Sign::Minus {
+            d + &self.q
+        } else {
+            d
+        }
+    }
+
+    fn scalar_mul(&self, a: &BigInt, b: &BigInt) -> BigInt {
+        (a * b) % &self.q
+    }
+
+    /// Uses Fermat's little theorem, `a^(q - 2) = a^(-1) mod q`, since `q` is prime.
+    fn scalar_invert(&self, a: &BigInt) -> Option<BigInt> {
+        let a = self.reduce(a, &self.q);
+        if a.sign() == Sign::NoSign {
+            return None;
+        }
+        Some(a.modpow(&(&self.q - 2), &self.q))
+    }
+
+    fn scalar_mul_sub_secret(&self, k: &BigInt, c: &BigInt, x: &BigInt) -> BigInt {
+        let [k, c, x] = [k, c, x].map(|n| self.reduce(n, &self.q));
+        ct::mul_sub(&k, &c, &x, &self.q)
+    }
+
+    fn scalar_from_u128(&self, n: u128) -> BigInt {
+        BigInt::from(n) % &self.q
+    }
+
+    /// Exponentiates `g` and `h` with their fixed-base tables, and the other bases together with Straus' algorithm.
+    /// Up to two other bases are exponentiated separately with `BigInt::modpow`, which is as fast for so few bases.
+    fn multi_exp(&self, terms: &[(&BigInt, &BigInt)]) -> BigInt {
+        let mut result = BigInt::one();
+        let mut variable = Vec::with_capacity(terms.len());
+        for (base, exponent) in terms {
+            match self.fixed_base(base).and_then(|table| table.exp(exponent)) {
+                Some(power) => result = (result * power) % &self.p,
+                None => variable.push((*base, *exponent)),
+            }
+        }
+        if variable.len() > 2 {
+            return (result * multiexp::straus(&variable, &self.p)) % &self.p;
+        }
+        variable
+            .into_iter()
+            .fold(result % &self.p, |acc, (base, exponent)| {
+                (acc * base.modpow(exponent, &self.p)) % &self.p
+            })
+    }
+
+    fn encode_element(&self, e: &BigInt) -> Vec<u8> {
+        to_fixed_bytes(e, self.element_len())
+    }
+
+    fn decode_element(&self, bytes: &[u8]) -> Option<BigInt> {
+        let e = BigInt::from_bytes_be(Sign::Plus, bytes);
+        (bytes.len() == self.element_len() && e < self.p).then_some(e)
+    }
+
+    fn encode_scalar(&self, s: &BigInt) -> Vec<u8> {
+        to_fixed_bytes(s, self.scalar_len())
+    }
+
+    fn decode_scalar(&self, bytes: &[u8]) -> Option<BigInt> {
+        let s = BigInt::from_bytes_be(Sign::Plus, bytes);
+        (bytes.len() == self.scalar_len() && s < self.q).then_some(s)
+    }
+
+    /// Returns the identifier of the named group, or `encoding::CUSTOM_GROUP_ID` for generated parameters.
+    fn group_id(&self) -> u8 {
+        self.group
+            .map_or(encoding::CUSTOM_GROUP_ID, |group| group.id())
+    }
+
+    /// Encodes `p`, `q`, `g` and `h`, each prefixed with its length.
+    fn encode_params(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for n in [&self.p, &self.q, &self.g, &self.h] {
+            let (_, n_bytes) = n.to_bytes_be();
+            bytes.extend_from_slice(&(n_bytes.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(&n_bytes);
+        }
+        bytes
+    }
+
+    /// Expands `input` with SHA-512 to 16 bytes more than the length of `q`, and reduces the result modulo `q`.
+    fn hash_to_scalar(&self, input: &[u8]) -> BigInt {
+        let len = self.scalar_len() + 16;
+        let mut bytes = Vec::with_capacity(len + 64);
+        for block in 0u32.. {
+            if bytes.len() >= len {
+                break;
+            }
+            let digest = Sha512::new()
+                .chain_update(block.to_be_bytes())
+                .chain_update(input)
+                .finalize();
+            bytes.extend_from_slice(&digest);
+        }
+        BigInt::from_bytes_be(Sign::Plus, &bytes[..len]) % &self.q
+    }
+}
+
+/// The output of `Material::builder`: the material if its moduli pass `Material::check_moduli`.
+impl From<Material> for Result<Material, MaterialError> {
+    fn from(material: Material) -> Self {
+        material.check_moduli()?;
+        Ok(material)
     }
 }
 
 impl Default for Material {
     fn default() -> Self {
-        Material::generate(None)
+        Material::from_named_group(NamedGroup::Ffdhe2048)
     }
 }
 
+/// Serializable representation of the material.
+///
+/// Material built from a named group only carries the group name and the seed of `h`. Otherwise `p`, `q`, `g` and `h`
+/// are encoded as hexadecimal strings.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MaterialSerde {
     pub user: String,
-    pub g: String,
-    pub h: String,
-    pub q: String,
-    pub p: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<NamedGroup>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub g: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub h: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub q: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub p: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub h_seed: Option<String>,
+    /// The hexadecimal `Group::fingerprint` of the material, checked when parsing it if present.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+    /// The hexadecimal public salt from which `password::derive_x` derives the secret of the user, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub salt: Option<String>,
 }
 
 impl MaterialSerde {
+    /// Builds the serializable representation of the material of a user.
     pub fn from_material(material: &Material, user: &str) -> Self {
+        let fingerprint = Some(to_hex(&material.fingerprint()));
+        match material.group {
+            Some(group) => MaterialSerde {
+                user: user.to_string(),
+                group: Some(group),
+                g: None,
+                h: None,
+                q: None,
+                p: None,
+                h_seed: material.h_seed.clone(),
+                fingerprint,
+                salt: None,
+            },
+            None => MaterialSerde {
+                user: user.to_string(),
+                group: None,
+                g: Some(material.g.to_str_radix(16)),
+                h: Some(material.h.to_str_radix(16)),
+                q: Some(material.q.to_str_radix(16)),
+                p: Some(material.p.to_str_radix(16)),
+                h_seed: material.h_seed.clone(),
+                fingerprint,
+                salt: None,
+            },
+        }
+    }
+
+    /// Records the public salt of the user, for secrets derived from a password.
+    pub fn with_salt(self, salt: &[u8]) -> Self {
         MaterialSerde {
-            user: user.to_string(),
-            g: material.g.to_str_radix(16),
-            h: material.h.to_str_radix(16),
-            q: material.q.to_str_radix(16),
-            p: material.p.to_str_radix(16),
+            salt: Some(to_hex(salt)),
+            ..self
         }
     }
 
-    pub fn to_material(&self) -> Material {
-        Material {
-            g: BigInt::parse_bytes(self.g.as_bytes(), 16).unwrap(),
-            h: BigInt::parse_bytes(self.h.as_bytes(), 16).unwrap(),
-            q: BigInt::parse_bytes(self.q.as_bytes(), 16).unwrap(),
-            p: BigInt::parse_bytes(self.p.as_bytes(), 16).unwrap(),
+    /// Decodes the public salt of the user.
+    ///
+    /// # Returns
+    ///
+    /// The salt, `None` if there is none, or `ProtocolError::InvalidHex` if it is not a hexadecimal string.
+    pub fn decode_salt(&self) -> Result<Option<Vec<u8>>, ProtocolError> {
+        self.salt
+            .as_deref()
+            .map(|salt| from_hex(salt).ok_or(ProtocolError::InvalidHex("salt")))
+            .transpose()
+    }
+
+    /// Parses the material.
+    ///
+    /// The parameters are only parsed, `Material::validate` checks them.
+    ///
+    /// # Returns
+    ///
+    /// The material, or `ProtocolError::MissingParameter` if there is no named group and any of `p`, `q`, `g` and
+    /// `h` is missing, `ProtocolError::InvalidHex` if any of them is not a hexadecimal number, or
+    /// `ProtocolError::FingerprintMismatch` if the recorded fingerprint is not the one of the parsed material.
+    pub fn to_material(&self) -> Result<Material, ProtocolError> {
+        let material = self.parse_material()?;
+        match &self.fingerprint {
+            Some(fingerprint)
+                if !fingerprint.eq_ignore_ascii_case(&to_hex(&material.fingerprint())) =>
+            {
+                Err(ProtocolError::FingerprintMismatch)
+            }
+            _ => Ok(material),
         }
     }
+
+    /// Parses the material, without checking its fingerprint.
+    fn parse_material(&self) -> Result<Material, ProtocolError> {
+        if let Some(group) = self.group {
+            let material = Material::from_named_group(group);
+            return Ok(match &self.h_seed {
+                Some(seed) => material.with_h_seed(seed),
+                None => material,
+            });
+        }
+        let parse = |name: &'static str, v: &Option<String>| {
+            let v = v.as_ref().ok_or(ProtocolError::MissingParameter(name))?;
+            BigInt::parse_bytes(v.as_bytes(), 16).ok_or(ProtocolError::InvalidHex(name))
+        };
+        let material = Material {
+            g: parse("g", &self.g)?,
+            h: parse("h", &self.h)?,
+            q: parse("q", &self.q)?,
+            p: parse("p", &self.p)?,
+            group: None,
+            h_seed: self.h_seed.clone(),
+            tables: FixedBaseCache::default(),
+        };
+        material.check_moduli()?;
+        Ok(material)
+    }
 }
 
 #[derive(Debug, Clone, TypedBuilder)]
-pub struct Challenge {
+pub struct Challenge<G: Group = Material> {
     #[builder(setter(into))]
     pub auth_id: AuthId,
     #[builder(setter(into))]
-    pub c: BigInt,
+    pub c: G::Scalar,
 }
 
-#[derive(Debug, Clone, TypedBuilder)]
-pub struct ChallengeResponse {
-    pub challenge: Challenge,
-    pub material: Material,
+#[derive(Debug, TypedBuilder)]
+pub struct ChallengeResponse<G: Group = Material> {
+    pub challenge: Challenge<G>,
+    pub material: G,
     #[builder(setter(into))]
-    pub x: BigInt,
+    pub x: Secret<G::Scalar>,
     #[builder(setter(into))]
-    pub k: BigInt,
+    pub k: Secret<G::Scalar>,
 }
 
 #[derive(Debug, Clone, TypedBuilder)]
-pub struct VerificationRequest {
+pub struct VerificationRequest<G: Group = Material> {
     pub auth_id: AuthId,
-    pub s: BigInt,
+    pub s: G::Scalar,
 }
 
 #[derive(Debug, Clone, Eq, Hash, PartialEq)]
 pub enum VerificationResult {
     ChallengeVerifiedSuccess,
     ChallengeVerificationFailed,
+    ChallengeRejected(RejectionReason),
 }
 
-#[derive(Debug, Clone, TypedBuilder)]
-pub struct Commitment {
-    pub material: Material,
-    pub r1: BigInt,
-    pub r2: BigInt,
-    pub k: BigInt,
+impl VerificationResult {
+    /// Converts the result into a `Result`, with a `ProtocolError` unless the challenge was verified.
+    pub fn into_result(self) -> Result<(), ProtocolError> {
+        match self {
+            VerificationResult::ChallengeVerifiedSuccess => Ok(()),
+            VerificationResult::ChallengeVerificationFailed => {
+                Err(ProtocolError::VerificationFailed)
+            }
+            VerificationResult::ChallengeRejected(reason) => Err(reason.into()),
+        }
+    }
 }
 
-#[derive(Debug, Clone, TypedBuilder)]
-pub struct Verification {
-    pub material: Material,
-    #[builder(setter(into))]
-    pub y1: BigInt,
-    #[builder(setter(into))]
-    pub y2: BigInt,
-    #[builder(setter(into))]
-    pub r1: BigInt,
+#[derive(Debug, TypedBuilder)]
+pub struct Commitment<G: Group = Material> {
+    pub material: G,
+    /// The bases `b_i` of the registration.
+    pub bases: Vec<G::Element>,
+    /// The commitments `r_i = b_i^k`, one per base.
+    pub rs: Vec<G::Element>,
     #[builder(setter(into))]
-    pub r2: BigInt,
+    pub k: Secret<G::Scalar>,
+}
+
+impl<G: Group> Commitment<G> {
+    /// Returns `r1`, the commitment of the first base.
+    ///
+    /// # Panics
+    ///
+    /// If there are no bases.
+    pub fn r1(&self) -> &G::Element {
+        &self.rs[0]
+    }
+
+    /// Returns `r2`, the commitment of the second base.
+    ///
+    /// # Panics
+    ///
+    /// If there are less than two bases.
+    pub fn r2(&self) -> &G::Element {
+        &self.rs[1]
+    }
+}
+
+#[derive(Debug, Clone, TypedBuilder)]
+pub struct Verification<G: Group = Material> {
+    pub material: G,
+    /// The bases `b_i`, by default `g` and `h`.
+    #[builder(default_code = "vec![material.g().clone(), material.h().clone()]")]
+    pub bases: Vec<G::Element>,
+    /// The public values `y_i`, `y1` and `y2` for the default bases.
+    pub ys: Vec<G::Element>,
+    /// The commitments `r_i`, `r1` and `r2` for the default bases.
+    pub rs: Vec<G::Element>,
     #[builder(setter(into))]
-    pub c: BigInt,
+    pub c: G::Scalar,
     #[builder(setter(into))]
-    pub s: BigInt,
+    pub s: G::Scalar,
+}
+
+impl<G: Group> Verification<G> {
+    /// Validates the received values before they are used in any exponentiation.
+    ///
+    /// Checks that there is one public value and one commitment per base, that the bases, the public values and the
+    /// commitments are non-trivial elements of the group, and that `c` and `s` are reduced modulo the group order,
+    /// that is in the range `[0, q)`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` which is `Ok(())` if all values are valid, or the `RejectionReason` of the first invalid value.
+    pub fn validate(&self) -> Result<(), RejectionReason> {
+        dleq::validate(
+            &self.material,
+            &self.bases,
+            &self.ys,
+            &self.rs,
+            &self.c,
+            &self.s,
+        )
+    }
 }
 
 /// Trait Type State Pattern
 pub trait ProtocolStep {}
-impl ProtocolStep for Register {}
-impl ProtocolStep for Commitment {}
-impl ProtocolStep for Challenge {}
-impl ProtocolStep for ChallengeResponse {}
+impl<G: Group> ProtocolStep for Register<G> {}
+impl<G: Group> ProtocolStep for Commitment<G> {}
+impl<G: Group> ProtocolStep for Challenge<G> {}
+impl<G: Group> ProtocolStep for ChallengeResponse<G> {}
 impl ProtocolStep for Material {}
-impl ProtocolStep for VerificationRequest {}
-impl ProtocolStep for Verification {}
+impl<G: Group> ProtocolStep for VerificationRequest<G> {}
+impl<G: Group> ProtocolStep for Verification<G> {}
 impl ProtocolStep for VerificationResult {}
 
 #[derive(Debug, Clone)]
@@ -210,114 +885,266 @@ where
     }
 }
 
-pub trait ProtocolTransition {
+pub trait ProtocolTransition: Sized {
     type NewState: ProtocolStep;
-    fn change(self) -> ProtocolState<Self::NewState>;
-}
 
-impl ProtocolTransition for ProtocolState<Register> {
-    type NewState = Commitment;
+    /// Moves to the next step, drawing any randomness it needs from `rand::thread_rng()`.
     fn change(self) -> ProtocolState<Self::NewState> {
-        let k = &rand::thread_rng().gen_bigint_range(&2.into(), &(&self.state.material.q - 2));
-        let p = &self.state.material.p;
-        let r1 = self.state.material.g.modpow(k, p);
-        let r2 = self.state.material.h.modpow(k, p);
+        self.change_with_rng(&mut rand::thread_rng())
+    }
+
+    /// Moves to the next step, drawing any randomness it needs from the given RNG.
+    ///
+    /// # Arguments
+    ///
+    /// * `rng` - The cryptographically secure RNG to use. Seeding it makes the transition reproducible, which is only
+    ///   meant for tests.
+    fn change_with_rng<R: RngCore + CryptoRng>(self, rng: &mut R) -> ProtocolState<Self::NewState>;
+}
+
+impl<G: Group> ProtocolTransition for ProtocolState<Register<G>> {
+    type NewState = Commitment<G>;
+    fn change_with_rng<R: RngCore + CryptoRng>(self, rng: &mut R) -> ProtocolState<Self::NewState> {
+        let k = self.state.material.random_scalar(rng);
+        self.commit(k)
+    }
+}
+
+impl<G: Group> ProtocolState<Register<G>> {
+    /// Moves to the `Commitment` step with a hedged nonce `k`, derived from `x`, the transcript and fresh randomness
+    /// as described in the `nonce` module, instead of from the system RNG alone.
+    ///
+    /// The transcript must hold a value unique to the session (`Transcript::append_session`), since the commitment is
+    /// answered to a challenge the transcript cannot contain.
+    ///
+    /// # Arguments
+    ///
+    /// * `x` - The secret the user registered with.
+    /// * `transcript` - The transcript holding the context of the proof, such as the user and the verifier.
+    pub fn change_hedged(
+        self,
+        x: &G::Scalar,
+        transcript: &Transcript,
+    ) -> ProtocolState<Commitment<G>> {
+        self.change_hedged_with_rng(x, transcript, &mut rand::thread_rng())
+    }
+
+    /// Same as `change_hedged`, drawing the fresh randomness from the given RNG.
+    pub fn change_hedged_with_rng<R: RngCore + CryptoRng>(
+        self,
+        x: &G::Scalar,
+        transcript: &Transcript,
+        rng: &mut R,
+    ) -> ProtocolState<Commitment<G>> {
+        let k = nonce::hedged_nonce(&self.state.material, x, transcript, rng);
+        self.commit(k)
+    }
+
+    fn commit(self, k: G::Scalar) -> ProtocolState<Commitment<G>> {
+        let Register {
+            material, bases, ..
+        } = self.state;
+        let rs = dleq::commit(&material, &bases, &k);
         ProtocolState {
             state: Commitment {
-                material: self.state.material.clone(),
-                r1,
-                r2,
-                k: k.clone(),
+                material,
+                bases,
+                rs,
+                k: Secret::new(k),
             },
         }
     }
 }
 
-impl ProtocolTransition for ProtocolState<Material> {
-    type NewState = Challenge;
-    fn change(self) -> ProtocolState<Self::NewState> {
-        let q = &self.state.q;
-        let c = rand::thread_rng().gen_bigint_range(&2.into(), &(q - 1));
+impl<G: Group> ProtocolTransition for ProtocolState<G> {
+    type NewState = Challenge<G>;
+    fn change_with_rng<R: RngCore + CryptoRng>(self, rng: &mut R) -> ProtocolState<Self::NewState> {
+        let c = self.state.random_scalar(rng);
+        let auth_id = Builder::from_random_bytes(rng.gen()).into_uuid();
         ProtocolState {
             state: Challenge {
-                auth_id: AuthId(Uuid::new_v4().to_string()),
+                auth_id: AuthId(auth_id.to_string()),
                 c,
             },
         }
     }
 }
 
-impl ProtocolTransition for ProtocolState<ChallengeResponse> {
-    type NewState = VerificationRequest;
-    fn change(self) -> ProtocolState<Self::NewState> {
-        let c: BigInt = self.state.challenge.c;
-        let q = &self.state.material.q;
-        let k = self.state.k;
-        let cx = c * &self.state.x;
-        let one = &BigInt::one();
-        let s = if k > cx {
-            (k - cx).modpow(one, q)
-        } else {
-            q - (cx - k).modpow(one, q)
-        };
-
+impl<G: Group> ProtocolTransition for ProtocolState<ChallengeResponse<G>> {
+    type NewState = VerificationRequest<G>;
+    /// The response is deterministic, so the RNG is not used.
+    fn change_with_rng<R: RngCore + CryptoRng>(self, _: &mut R) -> ProtocolState<Self::NewState> {
+        let ChallengeResponse {
+            challenge,
+            material,
+            x,
+            k,
+        } = self.state;
+        let s = ChaumPedersen::new(material).respond(x.expose(), k, &challenge.c);
         ProtocolState {
             state: VerificationRequest {
-                auth_id: self.state.challenge.auth_id,
+                auth_id: challenge.auth_id,
                 s,
             },
         }
     }
 }
 
-impl ProtocolState<Verification> {
+impl<G: Group> ProtocolState<Verification<G>> {
     pub fn change(self) -> ProtocolState<VerificationResult> {
-        let c = &self.state.c;
-        let y1 = &self.state.y1;
-        let y2 = &self.state.y2;
-        let r1 = &self.state.r1;
-        let r2 = &self.state.r2;
-        let g = &self.state.material.g;
-        let h = &self.state.material.h;
-        let p = &self.state.material.p;
-        let s = &self.state.s;
-        let one = &BigInt::one();
-        let r1_prime = (g.modpow(s, p) * y1.modpow(c, p)).modpow(one, p);
-        let r2_prime = (h.modpow(s, p) * y2.modpow(c, p)).modpow(one, p);
-        if r1 == &r1_prime && r2 == &r2_prime {
-            tracing::info!("Challenge verified successfully");
-            ProtocolState {
-                state: VerificationResult::ChallengeVerifiedSuccess,
-            }
-        } else {
-            tracing::info!(
-                "Challenge verification failed due to mismatch - expected: {:?}, actual: {:?}",
-                (r1_prime, r2_prime),
-                (r1, r2)
-            );
-            ProtocolState {
-                state: VerificationResult::ChallengeVerificationFailed,
-            }
+        let Verification {
+            material,
+            bases,
+            ys,
+            rs,
+            c,
+            s,
+        } = self.state;
+        ProtocolState {
+            state: dleq::verify(&material, &bases, &ys, &rs, &c, &s),
         }
     }
 }
 
+/// Chaum-Pedersen proof of knowledge of `x` such that `y1 = g^x` and `y2 = h^x`, as a `SigmaProtocol`.
+///
+/// It is the case of `dleq::Dleq` with the bases `g` and `h`, keeping the named fields of the wire format.
+/// The typestate steps above run this protocol with their default bases, adding the `AuthId` of the session and
+/// keeping the secrets of the prover between the steps.
+#[derive(Debug, Clone)]
+pub struct ChaumPedersen<G: Group = Material> {
+    pub material: G,
+}
+
+/// The public values `y1 = g^x` and `y2 = h^x` of a Chaum-Pedersen proof.
+#[derive(Debug, Clone, PartialEq, TypedBuilder)]
+pub struct ChaumPedersenStatement<G: Group = Material> {
+    pub y1: G::Element,
+    pub y2: G::Element,
+}
+
+impl<G: Group> ChaumPedersenStatement<G> {
+    /// Computes the statement of the secret `x`.
+    pub fn new(material: &G, x: &G::Scalar) -> Self {
+        let [y1, y2] = two(dleq::commit(material, [material.g(), material.h()], x));
+        ChaumPedersenStatement { y1, y2 }
+    }
+}
+
+/// The commitments `r1 = g^k` and `r2 = h^k` of a Chaum-Pedersen proof.
+#[derive(Debug, Clone, PartialEq, TypedBuilder)]
+pub struct ChaumPedersenCommitment<G: Group = Material> {
+    pub r1: G::Element,
+    pub r2: G::Element,
+}
+
+impl<G: Group> ChaumPedersen<G> {
+    /// Creates the protocol over the given group.
+    pub fn new(material: G) -> Self {
+        ChaumPedersen { material }
+    }
+
+    /// Computes the commitments `r1 = g^k` and `r2 = h^k` for the given nonce.
+    pub fn commit_with_nonce(&self, k: &G::Scalar) -> ChaumPedersenCommitment<G> {
+        let material = &self.material;
+        let [r1, r2] = two(dleq::commit(material, [material.g(), material.h()], k));
+        ChaumPedersenCommitment { r1, r2 }
+    }
+}
+
+/// Unpacks the two values `dleq` computes for the bases `g` and `h`.
+fn two<T>(values: Vec<T>) -> [T; 2] {
+    values
+        .try_into()
+        .unwrap_or_else(|_| unreachable!("one value per base"))
+}
+
+impl<G: Group> SigmaProtocol for ChaumPedersen<G> {
+    type Group = G;
+    type Statement = ChaumPedersenStatement<G>;
+    /// The secret `x`.
+    type Witness = G::Scalar;
+    type Commitment = ChaumPedersenCommitment<G>;
+    /// The nonce `k`.
+    type Nonce = Secret<G::Scalar>;
+    /// The response `s = k - c * x`.
+    type Response = G::Scalar;
+
+    fn material(&self) -> &G {
+        &self.material
+    }
+
+    fn commit<R: RngCore + CryptoRng>(
+        &self,
+        _: &ChaumPedersenStatement<G>,
+        _: &G::Scalar,
+        rng: &mut R,
+    ) -> (ChaumPedersenCommitment<G>, Secret<G::Scalar>) {
+        let k = self.material.random_scalar(rng);
+        (self.commit_with_nonce(&k), Secret::new(k))
+    }
+
+    fn respond(&self, x: &G::Scalar, k: Secret<G::Scalar>, c: &G::Scalar) -> G::Scalar {
+        self.material.scalar_mul_sub_secret(k.expose(), c, x)
+    }
+
+    /// Validates the values as `Verification::validate`, and checks that `r1 = g^s * y1^c` and `r2 = h^s * y2^c`.
+    fn verify(
+        &self,
+        statement: &ChaumPedersenStatement<G>,
+        commitment: &ChaumPedersenCommitment<G>,
+        c: &G::Scalar,
+        s: &G::Scalar,
+    ) -> VerificationResult {
+        let material = &self.material;
+        dleq::verify(
+            material,
+            &[material.g().clone(), material.h().clone()],
+            &[statement.y1.clone(), statement.y2.clone()],
+            &[commitment.r1.clone(), commitment.r2.clone()],
+            c,
+            s,
+        )
+    }
+
+    /// Uses `simulator::simulate_with_challenge`.
+    fn simulate<R: RngCore + CryptoRng>(
+        &self,
+        statement: &ChaumPedersenStatement<G>,
+        c: &G::Scalar,
+        rng: &mut R,
+    ) -> (ChaumPedersenCommitment<G>, G::Scalar) {
+        let simulated = simulator::simulate_with_challenge(
+            &self.material,
+            &statement.y1,
+            &statement.y2,
+            c.clone(),
+            rng,
+        );
+        let [r1, r2] = two(simulated.rs);
+        (ChaumPedersenCommitment { r1, r2 }, simulated.s)
+    }
+}
+
 #[cfg(test)]
 /// Module containing tests for the `cp` module.
 mod tests {
     use super::*;
+    use crate::protocol::test_support::known_material;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
 
     /// Test for the challenge transition change.
     #[test]
     fn test_challenge_transition_change() {
         // Initialize variables
         let x = BigInt::from(11);
-        let material = Material::default();
+        let material = Material::generate(256, None).unwrap();
         let register = Register::new(material.clone(), &x);
 
         // Create commit protocol
         let commit_proto = <Register as Into<ProtocolState<_>>>::into(register.clone()).change();
-        let commitment = commit_proto.clone().into_inner();
+        let commitment = commit_proto.into_inner();
 
         // Create challenge protocol
         let challenge_proto = <Material as Into<ProtocolState<_>>>::into(material).change();
@@ -339,10 +1166,8 @@ mod tests {
         // Create verification
         let verification = Verification::builder()
             .material(register.material)
-            .y1(register.y1)
-            .y2(register.y2)
-            .r1(commitment.r1)
-            .r2(commitment.r2)
+            .ys(register.ys)
+            .rs(commitment.rs)
             .c(challenge.c)
             .s(verification.s)
             .build();
@@ -353,5 +1178,394 @@ mod tests {
 
         // Assert the result
         assert_eq!(result, VerificationResult::ChallengeVerifiedSuccess);
+        assert_eq!(result.into_result(), Ok(()));
+    }
+
+    /// Test for a run of the protocol over more bases than `g` and `h`.
+    #[test]
+    fn test_challenge_transition_bases() {
+        let material = Material::generate(256, None).unwrap();
+        let bases: Vec<BigInt> = ["service-a", "service-b", "service-c"]
+            .iter()
+            .map(|service| Material::derive_h(&material.p, &material.g, service))
+            .collect();
+        let x = BigInt::from(11);
+        let register = Register::with_bases(material.clone(), bases.clone(), &x).unwrap();
+        assert_eq!(register.ys.len(), 3);
+        let commitment = ProtocolState::from(register.clone()).change().into_inner();
+        assert_eq!(commitment.bases, bases);
+        let challenge = ProtocolState::from(material.clone()).change().into_inner();
+        let response = ProtocolState::from(
+            ChallengeResponse::builder()
+                .challenge(challenge.clone())
+                .material(material.clone())
+                .x(x)
+                .k(commitment.k)
+                .build(),
+        )
+        .change()
+        .into_inner();
+        let verification = Verification::builder()
+            .material(material.clone())
+            .bases(bases)
+            .ys(register.ys.clone())
+            .rs(commitment.rs)
+            .c(challenge.c)
+            .s(response.s)
+            .build();
+        assert_eq!(
+            ProtocolState::from(verification.clone())
+                .change()
+                .into_inner(),
+            VerificationResult::ChallengeVerifiedSuccess
+        );
+
+        let defaults = Verification {
+            bases: vec![material.g.clone(), material.h.clone()],
+            ..verification.clone()
+        };
+        assert_eq!(defaults.validate(), Err(RejectionReason::WrongLength("ys")));
+        let mut ys = register.ys;
+        ys[2] = BigInt::from(1);
+        assert_eq!(
+            Verification {
+                ys,
+                ..verification.clone()
+            }
+            .validate(),
+            Err(RejectionReason::NotInGroup("ys"))
+        );
+        assert_eq!(
+            Verification {
+                bases: Vec::new(),
+                ys: Vec::new(),
+                rs: Vec::new(),
+                ..verification
+            }
+            .validate(),
+            Err(RejectionReason::NoBases)
+        );
+        assert!(Register::with_bases(material, Vec::new(), &BigInt::from(11)).is_err());
+    }
+
+    /// Test for the serialization of named and generated material.
+    #[test]
+    fn test_material_serde() {
+        let named = MaterialSerde::from_material(&Material::default(), "user");
+        let json = serde_json::to_string(&named).unwrap();
+        assert_eq!(
+            json,
+            concat!(
+                r#"{"user":"user","group":"ffdhe2048","h_seed":"zk-cp-protocol","fingerprint":"#,
+                r#""beeaedcac3c21ca228ce36a47961f7c1b41438290086770d3b11069d44966a06"}"#
+            )
+        );
+        let material = serde_json::from_str::<MaterialSerde>(&json)
+            .unwrap()
+            .to_material()
+            .unwrap();
+        assert_eq!(material.group, Some(NamedGroup::Ffdhe2048));
+        assert_eq!(material.p, NamedGroup::Ffdhe2048.p());
+
+        let generated = Material::generate(64, None).unwrap();
+        let json =
+            serde_json::to_string(&MaterialSerde::from_material(&generated, "user")).unwrap();
+        let material = serde_json::from_str::<MaterialSerde>(&json)
+            .unwrap()
+            .to_material()
+            .unwrap();
+        assert_eq!(material.group, None);
+        assert_eq!((material.p, material.q), (generated.p, generated.q));
+        assert_eq!((material.g, material.h), (generated.g, generated.h));
+    }
+
+    /// Test that the salt of the password derivation is stored in hex and decoded back.
+    #[test]
+    fn test_material_serde_salt() {
+        let salted =
+            MaterialSerde::from_material(&Material::default(), "user").with_salt(&[0, 1, 0xfe]);
+        assert_eq!(salted.salt.as_deref(), Some("0001fe"));
+        assert_eq!(salted.decode_salt(), Ok(Some(vec![0, 1, 0xfe])));
+        let malformed = MaterialSerde {
+            salt: Some("+1".to_string()),
+            ..salted
+        };
+        assert_eq!(
+            malformed.decode_salt(),
+            Err(ProtocolError::InvalidHex("salt"))
+        );
+    }
+
+    /// Test that malformed material and parameters are reported as errors instead of panicking.
+    #[test]
+    fn test_material_errors() {
+        let parse = |json: &str| {
+            serde_json::from_str::<MaterialSerde>(json)
+                .unwrap()
+                .to_material()
+                .map(|_| ())
+        };
+        assert_eq!(
+            parse(r#"{"user":"user","g":"4","h":"9","q":"b"}"#),
+            Err(ProtocolError::MissingParameter("p"))
+        );
+        assert_eq!(
+            parse(r#"{"user":"user","g":"4","h":"nine","q":"b","p":"17"}"#),
+            Err(ProtocolError::InvalidHex("h"))
+        );
+        assert_eq!(
+            parse(r#"{"user":"user","group":"ffdhe3072","fingerprint":"00"}"#),
+            Err(ProtocolError::FingerprintMismatch)
+        );
+        // Moduli that the constant-time arithmetic cannot use are refused before any exponentiation.
+        assert_eq!(
+            parse(r#"{"user":"user","g":"4","h":"9","q":"10","p":"17"}"#),
+            Err(MaterialError::QMismatch.into())
+        );
+        assert_eq!(
+            parse(r#"{"user":"user","g":"4","h":"9","q":"9","p":"14"}"#),
+            Err(MaterialError::InvalidModulus("p").into())
+        );
+        assert_eq!(
+            Material::builder().p(9).q(4).g(4).h(9).build().map(|_| ()),
+            Err(MaterialError::InvalidModulus("q"))
+        );
+        assert_eq!(
+            Material::builder()
+                .p(BigInt::one() << 16400)
+                .q(BigInt::one() << 16399)
+                .g(4)
+                .h(9)
+                .build()
+                .map(|_| ()),
+            Err(MaterialError::PTooLarge)
+        );
+        assert_eq!(
+            Material::generate(4, None).map(|_| ()),
+            Err(ProtocolError::UnsupportedBits {
+                min: MIN_BITS,
+                max: ct::MAX_BITS,
+                actual: 4
+            })
+        );
+        assert_eq!(
+            ProtocolError::from(MaterialError::PTooLarge).to_string(),
+            "invalid material: p has more than 16384 bits"
+        );
+        assert_eq!(
+            Material::generate(64, Some(BigInt::one())).map(|_| ()),
+            Err(MaterialError::InvalidGenerator("g").into())
+        );
+        assert_eq!(
+            Material {
+                p: BigInt::one() << 16400,
+                ..Material::default()
+            }
+            .validate(),
+            Err(MaterialError::PTooLarge)
+        );
+    }
+
+    /// Test for the derivation of `h` from a public seed.
+    #[test]
+    fn test_derive_h() {
+        let material = Material::default();
+        assert!(material.verify_h());
+        assert!(material.h.modpow(&material.q, &material.p).is_one());
+        assert_ne!(material.h, material.g);
+
+        let other = material.clone().with_h_seed("other seed");
+        assert!(other.verify_h());
+        assert_ne!(other.h, material.h);
+        assert_eq!(
+            other.h,
+            Material::derive_h(&material.p, &material.g, "other seed")
+        );
+
+        let generated = Material::generate(64, None).unwrap();
+        assert!(generated.verify_h());
+        assert!(generated.h.modpow(&generated.q, &generated.p).is_one());
+    }
+
+    /// Test that `h` is rejected if it does not match its seed, or if there is no seed.
+    #[test]
+    fn test_verify_h_failure() {
+        let material = Material::default();
+        let tampered = Material {
+            h: material.g.modpow(&BigInt::from(11), &material.p),
+            ..material.clone()
+        };
+        assert!(!tampered.verify_h());
+        let unseeded = Material {
+            h_seed: None,
+            ..material
+        };
+        assert!(!unseeded.verify_h());
+    }
+
+    /// Test for the validation of well formed material.
+    #[test]
+    fn test_validate() {
+        assert_eq!(Material::default().validate(), Ok(()));
+        assert_eq!(Material::generate(128, None).unwrap().validate(), Ok(()));
+    }
+
+    /// Test for the validation of malformed material.
+    #[test]
+    fn test_validate_failure() {
+        let material = Material::generate(64, None).unwrap();
+        let one = BigInt::one();
+        let with_p = |p: BigInt| Material {
+            q: (&p - 1) / 2,
+            p,
+            ..material.clone()
+        };
+        // 1031 is prime but 515 is not.
+        assert_eq!(
+            with_p(1031.into()).validate(),
+            Err(MaterialError::QNotPrime)
+        );
+        // 13 is prime but 27 is not.
+        assert_eq!(with_p(27.into()).validate(), Err(MaterialError::PNotPrime));
+        assert_eq!(
+            Material {
+                q: &material.q + 1,
+                ..material.clone()
+            }
+            .validate(),
+            Err(MaterialError::QMismatch)
+        );
+        for g in [
+            BigInt::from(0),
+            one.clone(),
+            &material.p - &one,
+            material.p.clone(),
+        ] {
+            assert_eq!(
+                Material {
+                    g,
+                    ..material.clone()
+                }
+                .validate(),
+                Err(MaterialError::InvalidGenerator("g"))
+            );
+        }
+        assert_eq!(
+            Material {
+                h: one,
+                ..material.clone()
+            }
+            .validate(),
+            Err(MaterialError::InvalidGenerator("h"))
+        );
+        assert_eq!(
+            Material {
+                h: material.g.clone(),
+                ..material
+            }
+            .validate(),
+            Err(MaterialError::EqualGenerators)
+        );
+    }
+
+    /// Test that malformed values are rejected before verification.
+    #[test]
+    fn test_verification_rejected() {
+        let material = Material::generate(64, None).unwrap();
+        let p = &material.p;
+        let q = &material.q;
+        let element = material.g.modpow(&BigInt::from(5), p);
+        let verification = Verification::builder()
+            .material(material.clone())
+            .ys(vec![element.clone(), element.clone()])
+            .rs(vec![element.clone(), element])
+            .c(BigInt::from(1))
+            .s(BigInt::from(1))
+            .build();
+        assert_eq!(verification.validate(), Ok(()));
+
+        let non_residue = p - 1;
+        for (y1, reason) in [
+            (BigInt::from(0), RejectionReason::NotInGroup("y1")),
+            (BigInt::from(1), RejectionReason::NotInGroup("y1")),
+            (non_residue, RejectionReason::NotInGroup("y1")),
+            (p + &material.g, RejectionReason::NotInGroup("y1")),
+        ] {
+            let result = ProtocolState::from(Verification {
+                ys: vec![y1, verification.ys[1].clone()],
+                ..verification.clone()
+            })
+            .change()
+            .into_inner();
+            assert_eq!(
+                result,
+                VerificationResult::ChallengeRejected(reason.clone())
+            );
+            assert_eq!(result.into_result(), Err(ProtocolError::Rejected(reason)));
+        }
+        assert_eq!(
+            Verification {
+                rs: vec![verification.rs[0].clone(), BigInt::from(1)],
+                ..verification.clone()
+            }
+            .validate(),
+            Err(RejectionReason::NotInGroup("r2"))
+        );
+        assert_eq!(
+            Verification {
+                c: q.clone(),
+                ..verification.clone()
+            }
+            .validate(),
+            Err(RejectionReason::OutOfRange("c"))
+        );
+        assert_eq!(
+            Verification {
+                s: BigInt::from(-1),
+                ..verification
+            }
+            .validate(),
+            Err(RejectionReason::OutOfRange("s"))
+        );
+    }
+
+    /// Known-answer test pinning a full run of the protocol with a seeded RNG.
+    #[test]
+    fn test_known_answer() {
+        let material = known_material();
+        assert_eq!(material.validate(), Ok(()));
+        let mut rng = ChaCha20Rng::seed_from_u64(2024);
+        let x = BigInt::from(1_234_567_890);
+        let register = Register::new(material.clone(), &x);
+        let commitment = ProtocolState::from(register.clone())
+            .change_with_rng(&mut rng)
+            .into_inner();
+        let challenge = ProtocolState::from(material)
+            .change_with_rng(&mut rng)
+            .into_inner();
+        let c = challenge.c.clone();
+        let response = ProtocolState::from(
+            ChallengeResponse::builder()
+                .challenge(challenge)
+                .material(commitment.material.clone())
+                .x(x)
+                .k(commitment.k)
+                .build(),
+        )
+        .change()
+        .into_inner();
+        assert_eq!(
+            commitment.rs,
+            [
+                BigInt::from(14_439_016_143_272_574_835u64),
+                BigInt::from(14_823_038_700_981_619_498u64)
+            ]
+        );
+        assert_eq!(c, BigInt::from(7_708_603_730_994_089_698u64));
+        assert_eq!(response.s, BigInt::from(1_705_334_755_272_376_241u64));
+        assert_eq!(
+            response.auth_id,
+            AuthId::from("66755328-d0d2-4c63-9e2f-6350987d59b3")
+        );
     }
 }
@@ -0,0 +1,195 @@
+//! This module contains the constant-time modular arithmetic used by `Material` for the secret values `x` and `k`.
+//!
+//! The values are converted to fixed-width `crypto_bigint::Uint`s with as many 64-bit limbs as the modulus needs,
+//! rounded up to the next supported size, and all the arithmetic runs in Montgomery form with `DynResidue`. The time
+//! taken by the operations only depends on the size of the modulus, never on the values of their operands.
+//!
+//! - `modpow`: Modular exponentiation with a secret exponent.
+//! - `mul_sub`: Computes `k - c * x` modulo `q` for the response of the prover.
+//! - `eq`: Compares two integers through their fixed-length encodings.
+//!
+//! Moduli of up to 16384 bits are supported, and must be odd. `Material::check_moduli` checks both whenever material
+//! is built from input, so malformed material is an error there instead of a panic here. The fixed-width copies of
+//! the operands are zeroized once the operation is done, as are the byte buffers of the conversions. The `BigInt`
+//! operands and results themselves are ordinary allocations that are not reliably wiped, see the `secret` module.
+use crypto_bigint::modular::runtime_mod::{DynResidue, DynResidueParams};
+use crypto_bigint::{Limb, Uint};
+use num_bigint::{BigInt, Sign};
+use subtle::ConstantTimeEq;
+use zeroize::Zeroize;
+
+/// Largest bit length of the moduli supported by the functions of this module.
+pub const MAX_BITS: usize = 16384;
+
+/// Calls `$f::<LIMBS>($args)` with the smallest supported number of limbs fitting a modulus of `$bits` bits.
+macro_rules! with_limbs {
+    ($bits:expr, $f:ident($($args:expr),*)) => {
+        match ($bits as usize).div_ceil(Limb::BITS) {
+            0..=1 => $f::<1>($($args),*),
+            2 => $f::<2>($($args),*),
+            3..=4 => $f::<4>($($args),*),
+            5..=8 => $f::<8>($($args),*),
+            9..=16 => $f::<16>($($args),*),
+            17..=32 => $f::<32>($($args),*),
+            33..=48 => $f::<48>($($args),*),
+            49..=64 => $f::<64>($($args),*),
+            65..=96 => $f::<96>($($args),*),
+            97..=128 => $f::<128>($($args),*),
+            129..=256 => $f::<256>($($args),*),
+            _ => panic!("moduli of more than {} bits are not supported", MAX_BITS),
+        }
+    };
+}
+
+/// Computes `base^exponent mod modulus` in constant time.
+///
+/// # Arguments
+///
+/// * `base` - The base, in the range `[0, modulus)`.
+/// * `exponent` - The secret exponent, in the range `[0, modulus)`.
+/// * `modulus` - An odd modulus.
+///
+/// # Panics
+///
+/// Panics if the modulus is even.
+pub fn modpow(base: &BigInt, exponent: &BigInt, modulus: &BigInt) -> BigInt {
+    with_limbs!(modulus.bits(), modpow_limbs(base, exponent, modulus))
+}
+
+fn modpow_limbs<const LIMBS: usize>(base: &BigInt, exponent: &BigInt, modulus: &BigInt) -> BigInt {
+    let params = DynResidueParams::new(&to_uint::<LIMBS>(modulus));
+    let base = DynResidue::new(&to_uint::<LIMBS>(base), params);
+    let mut exponent = to_uint::<LIMBS>(exponent);
+    let result = from_uint(&base.pow(&exponent).retrieve());
+    exponent.zeroize();
+    result
+}
+
+/// Computes `k - c * x mod modulus` in constant time.
+///
+/// # Arguments
+///
+/// * `k`, `c`, `x` - The operands, in the range `[0, modulus)`.
+/// * `modulus` - An odd modulus.
+///
+/// # Panics
+///
+/// Panics if the modulus is even.
+pub fn mul_sub(k: &BigInt, c: &BigInt, x: &BigInt, modulus: &BigInt) -> BigInt {
+    with_limbs!(modulus.bits(), mul_sub_limbs(k, c, x, modulus))
+}
+
+fn mul_sub_limbs<const LIMBS: usize>(
+    k: &BigInt,
+    c: &BigInt,
+    x: &BigInt,
+    modulus: &BigInt,
+) -> BigInt {
+    let params = DynResidueParams::new(&to_uint::<LIMBS>(modulus));
+    let residue = |n: &BigInt| {
+        let mut uint = to_uint::<LIMBS>(n);
+        let residue = DynResidue::new(&uint, params);
+        uint.zeroize();
+        residue
+    };
+    let [mut k, c, mut x] = [k, c, x].map(residue);
+    let mut s = k - c * x;
+    let result = from_uint(&s.retrieve());
+    for residue in [&mut k, &mut x, &mut s] {
+        residue.zeroize();
+    }
+    result
+}
+
+/// Compares two non-negative integers in constant time through their big-endian encodings of `len` bytes.
+pub fn eq(a: &BigInt, b: &BigInt, len: usize) -> bool {
+    to_fixed_bytes(a, len).ct_eq(&to_fixed_bytes(b, len)).into()
+}
+
+/// Encodes a non-negative integer as a big-endian byte string of at least `len` bytes.
+fn to_fixed_bytes(n: &BigInt, len: usize) -> Vec<u8> {
+    let (_, mut bytes) = n.to_bytes_be();
+    let mut fixed = vec![0; len.saturating_sub(bytes.len())];
+    fixed.extend_from_slice(&bytes);
+    bytes.zeroize();
+    fixed
+}
+
+/// Converts a non-negative integer to a `Uint` of `LIMBS` limbs.
+fn to_uint<const LIMBS: usize>(n: &BigInt) -> Uint<LIMBS> {
+    let mut bytes = to_fixed_bytes(n, LIMBS * Limb::BYTES);
+    assert_eq!(
+        bytes.len(),
+        LIMBS * Limb::BYTES,
+        "the value is larger than the modulus"
+    );
+    let uint = Uint::from_be_slice(&bytes);
+    bytes.zeroize();
+    uint
+}
+
+/// Converts a `Uint` back to a `BigInt`.
+fn from_uint<const LIMBS: usize>(n: &Uint<LIMBS>) -> BigInt {
+    let mut bytes: Vec<u8> = n
+        .as_words()
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .collect();
+    let result = BigInt::from_bytes_le(Sign::Plus, &bytes);
+    bytes.zeroize();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::groups::NamedGroup;
+    use num_bigint::RandBigInt;
+
+    fn moduli() -> Vec<BigInt> {
+        vec![
+            BigInt::from(23),
+            BigInt::from(2_305_843_009_213_693_951u64),
+            (BigInt::from(1) << 127) - 1,
+            NamedGroup::Ffdhe2048.p(),
+            NamedGroup::Modp3072.p(),
+        ]
+    }
+
+    #[test]
+    fn test_modpow() {
+        let mut rng = rand::thread_rng();
+        for p in moduli() {
+            for _ in 0..4 {
+                let base = rng.gen_bigint_range(&BigInt::from(0), &p);
+                let exponent = rng.gen_bigint_range(&BigInt::from(0), &p);
+                assert_eq!(
+                    modpow(&base, &exponent, &p),
+                    base.modpow(&exponent, &p),
+                    "p = {}",
+                    p
+                );
+            }
+            assert_eq!(modpow(&BigInt::from(5), &BigInt::from(0), &p), 1.into());
+        }
+    }
+
+    #[test]
+    fn test_mul_sub() {
+        let mut rng = rand::thread_rng();
+        for q in moduli() {
+            for _ in 0..4 {
+                let [k, c, x] = [(); 3].map(|_| rng.gen_bigint_range(&BigInt::from(0), &q));
+                let expected = ((&k - &c * &x) % &q + &q) % &q;
+                assert_eq!(mul_sub(&k, &c, &x, &q), expected, "q = {}", q);
+            }
+        }
+    }
+
+    #[test]
+    fn test_eq() {
+        assert!(eq(&BigInt::from(1234), &BigInt::from(1234), 4));
+        assert!(!eq(&BigInt::from(1234), &BigInt::from(1235), 4));
+        assert!(!eq(&BigInt::from(1234), &BigInt::from(1234 << 16), 4));
+    }
+}
@@ -0,0 +1,224 @@
+//! This module contains the hierarchical derivation of independent secrets from one master secret.
+//!
+//! A user authenticating to several independent verifiers should not register the same `y1` and `y2` with all of them,
+//! since the shared public values would let the verifiers link the accounts of the user. Instead, the master secret
+//! of the user is turned into one secret `x` per verifier identity or domain label:
+//! 1. `MasterKey::from_seed` expands the master secret into a key and a chain code with HMAC-SHA512.
+//! 2. `MasterKey::derive_child` derives the key of a label as `HMAC-SHA512(chain code, 0x00 || key || label)`, split
+//!    into the key and the chain code of the child. `MasterKey::derive_path` chains several labels, e.g. an
+//!    organisation and then one of its deployments.
+//! 3. `MasterKey::secret` turns a key into a non-zero scalar of the group, with the HMAC-SHA512 DRBG of `nonce`.
+//!
+//! Every derivation step is hardened, as in BIP-32: a child key depends on the secret key of its parent, so neither
+//! the public values nor the secrets of some labels reveal anything about the master secret or the other labels, and
+//! the `(y1, y2)` pairs of different labels cannot be linked to each other.
+use crate::protocol::error::ProtocolError;
+use crate::protocol::group::Group;
+use crate::protocol::nonce::NonceRng;
+use crate::protocol::secret::Secret;
+use hmac::{Hmac, Mac};
+use rand::{CryptoRng, RngCore};
+use sha2::Sha512;
+use std::fmt::{self, Debug, Formatter};
+use zeroize::Zeroize;
+
+/// Key of the HMAC expanding the master secret, as the "Bitcoin seed" key of BIP-32.
+const MASTER_DST: &[u8] = b"zk-cp-protocol/derivation/master/hmac-sha512/v1";
+
+/// Domain separation tag of the derivation of a secret from a key.
+const SECRET_DST: &[u8] = b"zk-cp-protocol/derivation/secret/v1";
+
+/// Minimum length in bytes of a master secret.
+pub const MIN_SEED_LEN: usize = 16;
+
+/// Length in bytes of the key and of the chain code.
+const KEY_LEN: usize = 32;
+
+/// Key of a node of the derivation tree: the master key or the key derived for a path of labels.
+///
+/// It is zeroized on drop, prints as `MasterKey([REDACTED])` in `Debug` and cannot be cloned.
+pub struct MasterKey {
+    key: [u8; KEY_LEN],
+    chain_code: [u8; KEY_LEN],
+}
+
+impl MasterKey {
+    /// Expands a master secret into the root of the derivation tree.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The master secret, of at least `MIN_SEED_LEN` bytes. The length is the only check that can be made:
+    ///   the seed must be drawn uniformly at random, such as the content of a file of random bytes, and not be a
+    ///   low-entropy value encoded over enough bytes.
+    ///
+    /// # Returns
+    ///
+    /// The master key, or `ProtocolError::TooShort` if the seed is shorter than `MIN_SEED_LEN`.
+    pub fn from_seed(seed: &[u8]) -> Result<Self, ProtocolError> {
+        if seed.len() < MIN_SEED_LEN {
+            return Err(ProtocolError::TooShort {
+                name: "master secret",
+                min: MIN_SEED_LEN,
+                actual: seed.len(),
+            });
+        }
+        Ok(MasterKey::expand(MASTER_DST, &[seed]))
+    }
+
+    /// Draws a random master secret of 32 bytes.
+    pub fn generate<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        let mut seed = [0u8; KEY_LEN];
+        rng.fill_bytes(&mut seed);
+        let master = MasterKey::expand(MASTER_DST, &[&seed]);
+        seed.zeroize();
+        master
+    }
+
+    /// Derives the key of a label, such as the identity of a verifier.
+    pub fn derive_child(&self, label: &str) -> MasterKey {
+        MasterKey::expand(&self.chain_code, &[&[0x00], &self.key, label.as_bytes()])
+    }
+
+    /// Derives the key of a path of labels, from the outermost to the innermost one.
+    pub fn derive_path(&self, labels: &[&str]) -> MasterKey {
+        labels
+            .iter()
+            .fold(self.copy(), |key, label| key.derive_child(label))
+    }
+
+    /// Derives the secret `x` of this key in the given group.
+    ///
+    /// The secret is bound to the `Group::fingerprint` of the material, so the same key gives unrelated secrets in
+    /// different groups.
+    pub fn secret<G: Group>(&self, material: &G) -> Secret<G::Scalar> {
+        let mut drbg = NonceRng::new(&[SECRET_DST, &self.key, &material.fingerprint()]);
+        Secret::new(material.random_scalar(&mut drbg))
+    }
+
+    /// Computes `HMAC-SHA512(key, data)` and splits it into the key and the chain code of a node.
+    fn expand(key: &[u8], data: &[&[u8]]) -> MasterKey {
+        let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts keys of any length");
+        for part in data {
+            mac.update(&(part.len() as u64).to_be_bytes());
+            mac.update(part);
+        }
+        let mut output: [u8; 2 * KEY_LEN] = mac.finalize().into_bytes().into();
+        let mut node = MasterKey {
+            key: [0; KEY_LEN],
+            chain_code: [0; KEY_LEN],
+        };
+        node.key.copy_from_slice(&output[..KEY_LEN]);
+        node.chain_code.copy_from_slice(&output[KEY_LEN..]);
+        output.zeroize();
+        node
+    }
+
+    /// Copies this key, for the start of a path. Not exposed as `Clone`, so that copies of secrets stay explicit.
+    fn copy(&self) -> MasterKey {
+        MasterKey {
+            key: self.key,
+            chain_code: self.chain_code,
+        }
+    }
+}
+
+/// Derives the secret `x` to register with a verifier from a master secret.
+///
+/// # Arguments
+///
+/// * `material` - The group of the verifier.
+/// * `seed` - The master secret of the user, of at least `MIN_SEED_LEN` bytes.
+/// * `domain` - The label of the verifier, such as its identity or address.
+///
+/// # Returns
+///
+/// The secret of the verifier, or `ProtocolError::TooShort` if the seed is shorter than `MIN_SEED_LEN`.
+pub fn derive_x<G: Group>(
+    material: &G,
+    seed: &[u8],
+    domain: &str,
+) -> Result<Secret<G::Scalar>, ProtocolError> {
+    Ok(MasterKey::from_seed(seed)?
+        .derive_child(domain)
+        .secret(material))
+}
+
+impl Drop for MasterKey {
+    fn drop(&mut self) {
+        self.key.zeroize();
+        self.chain_code.zeroize();
+    }
+}
+
+impl Debug for MasterKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("MasterKey([REDACTED])")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::cp::{ChaumPedersenStatement, Material};
+    use crate::protocol::ristretto::RistrettoMaterial;
+    use crate::protocol::test_support::known_material;
+    use num_bigint::BigInt;
+
+    const SEED: &[u8] = b"master secret of the user";
+
+    #[test]
+    fn test_derive() {
+        let material = Material::default();
+        let master = MasterKey::from_seed(SEED).unwrap();
+        let a = master.derive_child("verifier-a.example").secret(&material);
+        let b = master.derive_child("verifier-b.example").secret(&material);
+        assert_ne!(a.expose(), b.expose());
+        assert_eq!(
+            derive_x(&material, SEED, "verifier-a.example")
+                .unwrap()
+                .expose(),
+            a.expose()
+        );
+        assert_ne!(
+            ChaumPedersenStatement::new(&material, a.expose()).y1,
+            ChaumPedersenStatement::new(&material, b.expose()).y1
+        );
+
+        let path = master.derive_path(&["organisation", "deployment"]);
+        let chained = master
+            .derive_child("organisation")
+            .derive_child("deployment");
+        assert_eq!(
+            path.secret(&material).expose(),
+            chained.secret(&material).expose()
+        );
+        assert_ne!(
+            path.secret(&material).expose(),
+            master
+                .derive_child("organisation")
+                .secret(&material)
+                .expose()
+        );
+        assert_eq!(
+            master.derive_path(&[]).secret(&material).expose(),
+            master.secret(&material).expose()
+        );
+
+        let material = RistrettoMaterial::default();
+        let x = master.derive_child("verifier-a.example").secret(&material);
+        assert!(material.is_scalar(x.expose()));
+        assert_eq!(format!("{:?}", master), "MasterKey([REDACTED])");
+        assert_eq!(
+            MasterKey::from_seed(b"short").unwrap_err().to_string(),
+            "the master secret has 5 bytes, fewer than the minimum of 16"
+        );
+    }
+
+    /// Pins the secret derived for a label over the 64-bit material.
+    #[test]
+    fn test_known_answer() {
+        let material = known_material();
+        let x = derive_x(&material, SEED, "verifier-a.example").unwrap();
+        assert_eq!(x.expose(), &BigInt::from(4233197003120209656u64));
+    }
+}
@@ -0,0 +1,300 @@
+//! This module contains the proof of equality of discrete logarithms over any number of bases, as a `SigmaProtocol`.
+//!
+//! Given the bases `b_1, ..., b_n` and the public values `y_i = b_i^x`, the prover shows that all the `y_i` share the
+//! same secret `x`:
+//! 1. The prover commits to `r_i = b_i^k` for a random non-zero `k`.
+//! 2. The verifier sends a random challenge `c`.
+//! 3. The prover answers with `s = k - c * x`.
+//!
+//! The verifier accepts if `r_i = b_i^s * y_i^c` for every `i`. Chaum-Pedersen is the case `n = 2` with the bases
+//! `g` and `h`. The typestate steps of `cp` (`Register`, `Commitment` and `Verification`) carry their list of bases
+//! and run the functions of this module, as does `cp::ChaumPedersen`. With more bases, for instance one per service,
+//! derived with `Material::derive_h` from the name of the service, a single proof links the key of a user in all of
+//! them.
+use crate::protocol::cp::{Material, VerificationResult};
+use crate::protocol::error::{ProtocolError, RejectionReason};
+use crate::protocol::group::Group;
+use crate::protocol::secret::Secret;
+use crate::protocol::sigma::{self, SigmaProtocol};
+use crate::protocol::simulator;
+use rand::{CryptoRng, RngCore};
+
+/// Names of the public values in rejections: `y1` and `y2` for the first two bases, as in Chaum-Pedersen, and `ys`
+/// for the others.
+const Y_NAMES: [&str; 3] = ["y1", "y2", "ys"];
+
+/// Names of the commitments in rejections, as `Y_NAMES`.
+const R_NAMES: [&str; 3] = ["r1", "r2", "rs"];
+
+/// Proof of knowledge of `x` such that `y_i = b_i^x` for all the bases `b_i`.
+#[derive(Debug, Clone)]
+pub struct Dleq<G: Group = Material> {
+    pub material: G,
+    pub bases: Vec<G::Element>,
+}
+
+impl<G: Group> Dleq<G> {
+    /// Creates the protocol over the given group and bases.
+    ///
+    /// # Returns
+    ///
+    /// The protocol, or `ProtocolError::InvalidParameter` if there are no bases or any of them is not a non-trivial
+    /// element of the group.
+    pub fn new(material: G, bases: Vec<G::Element>) -> Result<Self, ProtocolError> {
+        if bases.is_empty() {
+            return Err(ProtocolError::InvalidParameter(
+                "the proof needs at least one base",
+            ));
+        }
+        if !bases.iter().all(|base| material.is_element(base)) {
+            return Err(ProtocolError::InvalidParameter(
+                "the bases must be non-trivial elements of the group",
+            ));
+        }
+        Ok(Dleq { material, bases })
+    }
+
+    /// Computes the statement `y_i = b_i^x` of the secret `x`.
+    pub fn statement(&self, x: &G::Scalar) -> Vec<G::Element> {
+        commit(&self.material, &self.bases, x)
+    }
+}
+
+/// Computes `b_i^k` for every base, in constant time.
+pub(crate) fn commit<'a, G: Group>(
+    material: &G,
+    bases: impl IntoIterator<Item = &'a G::Element>,
+    k: &G::Scalar,
+) -> Vec<G::Element>
+where
+    G::Element: 'a,
+{
+    bases
+        .into_iter()
+        .map(|base| material.exp_secret(base, k))
+        .collect()
+}
+
+/// Computes `b_i^s * y_i^c` for every base, which must be equal to the commitment `r_i` of an accepting transcript.
+pub(crate) fn recompute<'a, G: Group>(
+    material: &G,
+    bases: impl IntoIterator<Item = &'a G::Element>,
+    ys: impl IntoIterator<Item = &'a G::Element>,
+    c: &G::Scalar,
+    s: &G::Scalar,
+) -> Vec<G::Element>
+where
+    G::Element: 'a,
+{
+    bases
+        .into_iter()
+        .zip(ys)
+        .map(|(base, y)| material.multi_exp(&[(base, s), (y, c)]))
+        .collect()
+}
+
+/// Checks that the commitments are equal to the recomputed values, in constant time.
+pub(crate) fn matches<'a, G: Group>(
+    material: &G,
+    rs: impl IntoIterator<Item = &'a G::Element>,
+    recomputed: &[G::Element],
+) -> bool
+where
+    G::Element: 'a,
+{
+    rs.into_iter()
+        .zip(recomputed)
+        .fold(true, |acc, (r, r_prime)| {
+            acc & material.element_ct_eq(r, r_prime)
+        })
+}
+
+/// Checks the received values before they are used in any exponentiation.
+///
+/// Checks that there is at least one base, one public value and one commitment per base, that all of them are
+/// non-trivial elements of the group, and that `c` and `s` are reduced. The public values and the commitments are
+/// named as in `Y_NAMES` and `R_NAMES` in the rejections, and their lists as `ys` and `rs`.
+///
+/// # Returns
+///
+/// A `Result` which is `Ok(())` if all values are valid, or the `RejectionReason` of the first invalid value.
+pub(crate) fn validate<G: Group>(
+    material: &G,
+    bases: &[G::Element],
+    ys: &[G::Element],
+    rs: &[G::Element],
+    c: &G::Scalar,
+    s: &G::Scalar,
+) -> Result<(), RejectionReason> {
+    if bases.is_empty() {
+        return Err(RejectionReason::NoBases);
+    }
+    if !bases.iter().all(|base| material.is_element(base)) {
+        return Err(RejectionReason::NotInGroup("bases"));
+    }
+    for (list, values, names) in [("ys", ys, Y_NAMES), ("rs", rs, R_NAMES)] {
+        if values.len() != bases.len() {
+            return Err(RejectionReason::WrongLength(list));
+        }
+        for (i, value) in values.iter().enumerate() {
+            if !material.is_element(value) {
+                return Err(RejectionReason::NotInGroup(names[i.min(2)]));
+            }
+        }
+    }
+    sigma::validate(material, &[], &[("c", c), ("s", s)])
+}
+
+/// Validates the values with `validate`, and checks that `r_i = b_i^s * y_i^c` for every base.
+pub(crate) fn verify<G: Group>(
+    material: &G,
+    bases: &[G::Element],
+    ys: &[G::Element],
+    rs: &[G::Element],
+    c: &G::Scalar,
+    s: &G::Scalar,
+) -> VerificationResult {
+    if let Err(reason) = validate(material, bases, ys, rs, c, s) {
+        tracing::info!("Challenge rejected: {}", reason);
+        return VerificationResult::ChallengeRejected(reason);
+    }
+    let expected = recompute(material, bases, ys, c, s);
+    if matches(material, rs, &expected) {
+        tracing::info!("Challenge verified successfully");
+        VerificationResult::ChallengeVerifiedSuccess
+    } else {
+        tracing::info!(
+            "Challenge verification failed due to mismatch - expected: {:?}, actual: {:?}",
+            expected,
+            rs
+        );
+        VerificationResult::ChallengeVerificationFailed
+    }
+}
+
+impl<G: Group> SigmaProtocol for Dleq<G> {
+    type Group = G;
+    /// The public values `y_i = b_i^x`, one per base.
+    type Statement = Vec<G::Element>;
+    /// The secret `x`.
+    type Witness = G::Scalar;
+    /// The commitments `r_i = b_i^k`, one per base.
+    type Commitment = Vec<G::Element>;
+    /// The nonce `k`.
+    type Nonce = Secret<G::Scalar>;
+    /// The response `s = k - c * x`.
+    type Response = G::Scalar;
+
+    fn material(&self) -> &G {
+        &self.material
+    }
+
+    fn commit<R: RngCore + CryptoRng>(
+        &self,
+        _: &Vec<G::Element>,
+        _: &G::Scalar,
+        rng: &mut R,
+    ) -> (Vec<G::Element>, Secret<G::Scalar>) {
+        let k = self.material.random_scalar(rng);
+        (commit(&self.material, &self.bases, &k), Secret::new(k))
+    }
+
+    fn respond(&self, x: &G::Scalar, k: Secret<G::Scalar>, c: &G::Scalar) -> G::Scalar {
+        self.material.scalar_mul_sub_secret(k.expose(), c, x)
+    }
+
+    /// Validates the values as `validate` and checks that `r_i = b_i^s * y_i^c` for every base.
+    fn verify(
+        &self,
+        ys: &Vec<G::Element>,
+        rs: &Vec<G::Element>,
+        c: &G::Scalar,
+        s: &G::Scalar,
+    ) -> VerificationResult {
+        verify(&self.material, &self.bases, ys, rs, c, s)
+    }
+
+    /// Draws `s` uniformly and solves `r_i = b_i^s * y_i^c`, redrawing `s` whenever `r_1` would be the identity.
+    fn simulate<R: RngCore + CryptoRng>(
+        &self,
+        ys: &Vec<G::Element>,
+        c: &G::Scalar,
+        rng: &mut R,
+    ) -> (Vec<G::Element>, G::Scalar) {
+        let identity = self.material.identity();
+        loop {
+            let s = simulator::uniform_scalar(&self.material, rng);
+            let rs = recompute(&self.material, &self.bases, ys, c, &s);
+            if rs[0] != identity {
+                return (rs, s);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::ristretto::RistrettoMaterial;
+    use crate::protocol::test_support::run;
+    use num_bigint::BigInt;
+
+    #[test]
+    fn test_services() {
+        let material = Material::generate(256, None).unwrap();
+        let bases: Vec<BigInt> = ["service-a", "service-b", "service-c", "service-d"]
+            .iter()
+            .map(|service| Material::derive_h(&material.p, &material.g, service))
+            .collect();
+        let x = BigInt::from(1234);
+        for n in 1..=bases.len() {
+            let dleq = Dleq::new(material.clone(), bases[..n].to_vec()).unwrap();
+            let ys = dleq.statement(&x);
+            assert_eq!(
+                run(&dleq, &ys, &x),
+                VerificationResult::ChallengeVerifiedSuccess
+            );
+
+            let mut rng = rand::thread_rng();
+            let c = dleq.challenge(&mut rng);
+            let (rs, s) = dleq.simulate(&ys, &c, &mut rng);
+            assert_eq!(
+                dleq.verify(&ys, &rs, &c, &s),
+                VerificationResult::ChallengeVerifiedSuccess
+            );
+        }
+    }
+
+    #[test]
+    fn test_different_secrets() {
+        let material = RistrettoMaterial::default();
+        let mut rng = rand::thread_rng();
+        let bases: Vec<_> = (0..3)
+            .map(|_| material.exp(material.g(), &material.random_scalar(&mut rng)))
+            .collect();
+        let dleq = Dleq::new(material.clone(), bases).unwrap();
+        let x = material.random_scalar(&mut rng);
+        let mut ys = dleq.statement(&x);
+        assert_eq!(
+            run(&dleq, &ys, &x),
+            VerificationResult::ChallengeVerifiedSuccess
+        );
+
+        // The last key belongs to another secret.
+        ys[2] = material.exp(
+            &dleq.bases[2],
+            &material.scalar_add(&x, &material.scalar_from_u128(1)),
+        );
+        assert_eq!(
+            run(&dleq, &ys, &x),
+            VerificationResult::ChallengeVerificationFailed
+        );
+
+        ys.pop();
+        let (rs, _) = dleq.commit(&ys, &x, &mut rng);
+        assert_eq!(
+            dleq.verify(&ys, &rs, &x, &x),
+            VerificationResult::ChallengeRejected(RejectionReason::WrongLength("ys"))
+        );
+    }
+}
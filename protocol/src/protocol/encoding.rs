@@ -0,0 +1,565 @@
+//! This module contains the versioned canonical binary encoding of the protocol messages.
+//!
+//! Every encoded message starts with a header of three bytes:
+//! - The version of the encoding, `VERSION`.
+//! - The identifier of the group, `Group::group_id`: `CUSTOM_GROUP_ID` for generated safe-prime groups, the
+//!   identifier of the `NamedGroup` for well-known groups, and `RISTRETTO255_GROUP_ID` for Ristretto255.
+//! - The `MessageType`.
+//!
+//! The body follows, made of the fixed-length encodings of the elements and scalars of the group (big-endian and as
+//! long as `p` or `q` for `Material`, 32 bytes for Ristretto255), in the order of the fields of the message. Strings
+//! are prefixed with their length as a big-endian `u16`, so encoding fails with `ProtocolError::InvalidEncoding` for a
+//! string or a list of bases longer than `u16::MAX`. Decoding rejects any other version, a group or message type
+//! different from the expected one, non-canonical values and trailing bytes, so every message has exactly one
+//! encoding.
+//!
+//! The encoded messages are:
+//! - `Material`: For generated groups, the length of `p` as a `u16`, then `p`, `g` and `h`. For named groups, only `h`.
+//!   Both end with the optional seed of `h`, as a flag byte followed by the string.
+//! - `Register`: The number of bases as a `u16`, then the bases and the values `y_i`, one per base.
+//! - `ChaumPedersenCommitment`: `r1` and `r2`, the public part of a `Commitment` over two bases, whose nonce never
+//!   leaves the prover.
+//! - `Challenge`: The `AuthId`, as a string, and `c`.
+//! - `VerificationRequest`: The `AuthId` and `s`.
+//! - `Proof`: `r1`, `r2` and `s`.
+//!
+//! Except for `Material`, decoding needs the material the message belongs to, and checks its group identifier.
+use crate::protocol::cp::{
+    AuthId, Challenge, ChaumPedersenCommitment, Commitment, Material, Register, VerificationRequest,
+};
+use crate::protocol::error::ProtocolError;
+use crate::protocol::group::Group;
+use crate::protocol::groups::NamedGroup;
+use crate::protocol::nizk::Proof;
+use num_bigint::{BigInt, Sign};
+use num_traits::One;
+
+/// Version of the encoding, the first byte of every message.
+pub const VERSION: u8 = 1;
+
+/// Group identifier of the `Material` generated with `Material::generate`, whose parameters are not well-known.
+pub const CUSTOM_GROUP_ID: u8 = 0;
+
+/// Group identifier of `RistrettoMaterial`.
+pub const RISTRETTO255_GROUP_ID: u8 = 0x20;
+
+/// Type of an encoded message, the third byte of its header.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[repr(u8)]
+pub enum MessageType {
+    Material = 1,
+    Register = 2,
+    Commitment = 3,
+    Challenge = 4,
+    VerificationRequest = 5,
+    Proof = 6,
+}
+
+/// Message of the protocol with a canonical binary encoding.
+pub trait Message<G: Group>: Sized {
+    /// The type written in the header of the message.
+    const TYPE: MessageType;
+
+    /// Appends the body of the message to `out`.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())`, or `ProtocolError::InvalidEncoding` if a length of the message does not fit in a `u16`.
+    fn encode_body(&self, material: &G, out: &mut Vec<u8>) -> Result<(), ProtocolError>;
+
+    /// Reads the body of the message.
+    fn decode_body(material: &G, reader: &mut Reader) -> Result<Self, ProtocolError>;
+}
+
+/// Encodes a message, prefixed with its header.
+///
+/// # Arguments
+///
+/// * `material` - The group the message belongs to.
+/// * `message` - The message to encode.
+///
+/// # Returns
+///
+/// The encoded message, or `ProtocolError::InvalidEncoding` if a string or a list of the message is longer than
+/// `u16::MAX`.
+pub fn encode<G: Group, M: Message<G>>(
+    material: &G,
+    message: &M,
+) -> Result<Vec<u8>, ProtocolError> {
+    let mut out = vec![VERSION, material.group_id(), M::TYPE as u8];
+    message.encode_body(material, &mut out)?;
+    Ok(out)
+}
+
+/// Decodes a message encoded with `encode`.
+///
+/// # Arguments
+///
+/// * `material` - The group the message belongs to.
+/// * `bytes` - The encoded message.
+///
+/// # Returns
+///
+/// The decoded message, or a `ProtocolError` if the header does not match the version, the group or the type of the
+/// message, or the body is not a canonical encoding.
+pub fn decode<G: Group, M: Message<G>>(material: &G, bytes: &[u8]) -> Result<M, ProtocolError> {
+    let mut reader = Reader::new(bytes);
+    reader.header(material.group_id(), M::TYPE)?;
+    let message = M::decode_body(material, &mut reader)?;
+    reader.finish()?;
+    Ok(message)
+}
+
+/// Encodes the material, prefixed with its header.
+///
+/// # Returns
+///
+/// The encoded material, or `ProtocolError::InvalidEncoding` if `p` or the seed of `h` is longer than `u16::MAX`
+/// bytes.
+pub fn encode_material(material: &Material) -> Result<Vec<u8>, ProtocolError> {
+    let mut out = vec![VERSION, material.group_id(), MessageType::Material as u8];
+    if material.group.is_none() {
+        write_u16(&mut out, material.encode_element(&material.p).len(), "p")?;
+        out.extend_from_slice(&material.encode_element(&material.p));
+        out.extend_from_slice(&material.encode_element(&material.g));
+    }
+    out.extend_from_slice(&material.encode_element(&material.h));
+    match &material.h_seed {
+        Some(seed) => {
+            out.push(1);
+            write_string(&mut out, seed, "h_seed")?;
+        }
+        None => out.push(0),
+    }
+    Ok(out)
+}
+
+/// Decodes material encoded with `encode_material`.
+///
+/// The parameters are only decoded and checked with `Material::check_moduli`, `Material::validate` checks them fully.
+///
+/// # Returns
+///
+/// The material, or a `ProtocolError` if the header is not the one of a material of a known group, the body is not
+/// a canonical encoding, or `p` cannot be used as a modulus.
+pub fn decode_material(bytes: &[u8]) -> Result<Material, ProtocolError> {
+    let mut reader = Reader::new(bytes);
+    let group_id = reader.version()?;
+    let group = match group_id {
+        CUSTOM_GROUP_ID => None,
+        id => Some(NamedGroup::from_id(id).ok_or(ProtocolError::InvalidEncoding("unknown group"))?),
+    };
+    reader.message_type(MessageType::Material)?;
+    let mut material = match group {
+        Some(group) => Material::from_named_group(group),
+        None => {
+            let len = reader.u16()?;
+            let p = BigInt::from_bytes_be(Sign::Plus, reader.take(len)?);
+            if p.bits().div_ceil(8) != len as u64 || p <= BigInt::from(3) {
+                return Err(ProtocolError::InvalidEncoding("p"));
+            }
+            let q = (&p - BigInt::one()) / 2;
+            Material::builder()
+                .g(BigInt::one())
+                .h(BigInt::one())
+                .q(q)
+                .p(p)
+                .build()?
+        }
+    };
+    if group.is_none() {
+        material.g = reader.element(&material, "g")?;
+    }
+    material.h = reader.element(&material, "h")?;
+    material.h_seed = match reader.take(1)? {
+        [0] => None,
+        [1] => Some(reader.string("h_seed")?),
+        _ => return Err(ProtocolError::InvalidEncoding("h_seed")),
+    };
+    reader.finish()?;
+    Ok(material)
+}
+
+/// Reader of an encoded message, consuming it from the front.
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes }
+    }
+
+    /// Reads the next `len` bytes.
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], ProtocolError> {
+        if self.bytes.len() < len {
+            return Err(ProtocolError::InvalidEncoding("the message is truncated"));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    /// Reads a big-endian `u16`.
+    pub fn u16(&mut self) -> Result<usize, ProtocolError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]) as usize)
+    }
+
+    /// Reads a fixed-length element of the group, rejecting it with the given name if it is not canonical.
+    pub fn element<G: Group>(
+        &mut self,
+        material: &G,
+        name: &'static str,
+    ) -> Result<G::Element, ProtocolError> {
+        let len = material.encode_element(material.g()).len();
+        material
+            .decode_element(self.take(len)?)
+            .ok_or(ProtocolError::InvalidEncoding(name))
+    }
+
+    /// Reads a fixed-length scalar of the group, rejecting it with the given name if it is not canonical.
+    pub fn scalar<G: Group>(
+        &mut self,
+        material: &G,
+        name: &'static str,
+    ) -> Result<G::Scalar, ProtocolError> {
+        let len = material.encode_scalar(&material.scalar_from_u128(0)).len();
+        material
+            .decode_scalar(self.take(len)?)
+            .ok_or(ProtocolError::InvalidEncoding(name))
+    }
+
+    /// Reads a string prefixed with its length, rejecting it with the given name if it is not UTF-8.
+    pub fn string(&mut self, name: &'static str) -> Result<String, ProtocolError> {
+        let len = self.u16()?;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| ProtocolError::InvalidEncoding(name))
+    }
+
+    /// Reads the version, returning the group identifier that follows it.
+    fn version(&mut self) -> Result<u8, ProtocolError> {
+        match self.take(2)? {
+            [VERSION, group_id] => Ok(*group_id),
+            [version, _] => Err(ProtocolError::UnsupportedVersion(*version)),
+            _ => unreachable!("two bytes were taken"),
+        }
+    }
+
+    /// Reads the message type, checking it is the expected one.
+    fn message_type(&mut self, expected: MessageType) -> Result<(), ProtocolError> {
+        if self.take(1)? != [expected as u8] {
+            return Err(ProtocolError::InvalidEncoding("unexpected message type"));
+        }
+        Ok(())
+    }
+
+    /// Reads the whole header, checking the version, the group and the message type.
+    fn header(&mut self, group_id: u8, expected: MessageType) -> Result<(), ProtocolError> {
+        let found = self.version()?;
+        if found != group_id {
+            return Err(ProtocolError::GroupMismatch {
+                expected: group_id,
+                found,
+            });
+        }
+        self.message_type(expected)
+    }
+
+    /// Checks that the whole message was read.
+    fn finish(&self) -> Result<(), ProtocolError> {
+        if !self.bytes.is_empty() {
+            return Err(ProtocolError::InvalidEncoding("trailing bytes"));
+        }
+        Ok(())
+    }
+}
+
+/// Appends the length of the named value as a `u16`, or returns `ProtocolError::InvalidEncoding` if it does not fit.
+fn write_u16(out: &mut Vec<u8>, len: usize, name: &'static str) -> Result<(), ProtocolError> {
+    let len = u16::try_from(len).map_err(|_| ProtocolError::InvalidEncoding(name))?;
+    out.extend_from_slice(&len.to_be_bytes());
+    Ok(())
+}
+
+/// Appends a string prefixed with its length.
+fn write_string(out: &mut Vec<u8>, s: &str, name: &'static str) -> Result<(), ProtocolError> {
+    write_u16(out, s.len(), name)?;
+    out.extend_from_slice(s.as_bytes());
+    Ok(())
+}
+
+impl<G: Group> Message<G> for Register<G> {
+    const TYPE: MessageType = MessageType::Register;
+
+    fn encode_body(&self, material: &G, out: &mut Vec<u8>) -> Result<(), ProtocolError> {
+        write_u16(out, self.bases.len(), "bases")?;
+        for e in self.bases.iter().chain(&self.ys) {
+            out.extend_from_slice(&material.encode_element(e));
+        }
+        Ok(())
+    }
+
+    fn decode_body(material: &G, reader: &mut Reader) -> Result<Self, ProtocolError> {
+        let n = reader.u16()?;
+        if n == 0 {
+            return Err(ProtocolError::InvalidEncoding("bases"));
+        }
+        let bases = (0..n)
+            .map(|_| reader.element(material, "bases"))
+            .collect::<Result<_, _>>()?;
+        let ys = (0..n)
+            .map(|_| reader.element(material, "ys"))
+            .collect::<Result<_, _>>()?;
+        Ok(Register {
+            material: material.clone(),
+            bases,
+            ys,
+        })
+    }
+}
+
+impl<G: Group> TryFrom<&Commitment<G>> for ChaumPedersenCommitment<G> {
+    type Error = ProtocolError;
+
+    /// Takes the public part of a commitment over two bases, leaving the nonce with the prover.
+    fn try_from(commitment: &Commitment<G>) -> Result<Self, ProtocolError> {
+        match commitment.rs.as_slice() {
+            [r1, r2] => Ok(ChaumPedersenCommitment {
+                r1: r1.clone(),
+                r2: r2.clone(),
+            }),
+            _ => Err(ProtocolError::InvalidParameter(
+                "a Chaum-Pedersen commitment has two bases",
+            )),
+        }
+    }
+}
+
+impl<G: Group> Message<G> for ChaumPedersenCommitment<G> {
+    const TYPE: MessageType = MessageType::Commitment;
+
+    fn encode_body(&self, material: &G, out: &mut Vec<u8>) -> Result<(), ProtocolError> {
+        out.extend_from_slice(&material.encode_element(&self.r1));
+        out.extend_from_slice(&material.encode_element(&self.r2));
+        Ok(())
+    }
+
+    fn decode_body(material: &G, reader: &mut Reader) -> Result<Self, ProtocolError> {
+        Ok(ChaumPedersenCommitment {
+            r1: reader.element(material, "r1")?,
+            r2: reader.element(material, "r2")?,
+        })
+    }
+}
+
+impl<G: Group> Message<G> for Challenge<G> {
+    const TYPE: MessageType = MessageType::Challenge;
+
+    fn encode_body(&self, material: &G, out: &mut Vec<u8>) -> Result<(), ProtocolError> {
+        write_string(out, &self.auth_id, "auth_id")?;
+        out.extend_from_slice(&material.encode_scalar(&self.c));
+        Ok(())
+    }
+
+    fn decode_body(material: &G, reader: &mut Reader) -> Result<Self, ProtocolError> {
+        Ok(Challenge {
+            auth_id: AuthId(reader.string("auth_id")?),
+            c: reader.scalar(material, "c")?,
+        })
+    }
+}
+
+impl<G: Group> Message<G> for VerificationRequest<G> {
+    const TYPE: MessageType = MessageType::VerificationRequest;
+
+    fn encode_body(&self, material: &G, out: &mut Vec<u8>) -> Result<(), ProtocolError> {
+        write_string(out, &self.auth_id, "auth_id")?;
+        out.extend_from_slice(&material.encode_scalar(&self.s));
+        Ok(())
+    }
+
+    fn decode_body(material: &G, reader: &mut Reader) -> Result<Self, ProtocolError> {
+        Ok(VerificationRequest {
+            auth_id: AuthId(reader.string("auth_id")?),
+            s: reader.scalar(material, "s")?,
+        })
+    }
+}
+
+impl<G: Group> Message<G> for Proof<G> {
+    const TYPE: MessageType = MessageType::Proof;
+
+    fn encode_body(&self, material: &G, out: &mut Vec<u8>) -> Result<(), ProtocolError> {
+        out.extend_from_slice(&self.encode(material));
+        Ok(())
+    }
+
+    fn decode_body(material: &G, reader: &mut Reader) -> Result<Self, ProtocolError> {
+        Ok(Proof {
+            r1: reader.element(material, "r1")?,
+            r2: reader.element(material, "r2")?,
+            s: reader.scalar(material, "s")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::cp::{ProtocolState, ProtocolTransition};
+    use crate::protocol::error::MaterialError;
+    use crate::protocol::nizk;
+    use crate::protocol::ristretto::RistrettoMaterial;
+    use crate::protocol::test_support::{hex, known_material};
+    use crate::protocol::transcript::Transcript;
+
+    /// Checks that every message of a run of the protocol survives a round trip.
+    fn round_trip<G: Group>(material: G, x: &G::Scalar) {
+        let register = Register::new(material.clone(), x);
+        let decoded: Register<G> =
+            decode(&material, &encode(&material, &register).unwrap()).unwrap();
+        assert_eq!(decoded.bases, register.bases);
+        assert_eq!(decoded.ys, register.ys);
+
+        let commitment = ProtocolState::from(register).change().into_inner();
+        let public = ChaumPedersenCommitment::try_from(&commitment).unwrap();
+        let decoded: ChaumPedersenCommitment<G> =
+            decode(&material, &encode(&material, &public).unwrap()).unwrap();
+        assert_eq!((decoded.r1, decoded.r2), (public.r1, public.r2));
+
+        let challenge: Challenge<G> = ProtocolState::from(material.clone()).change().into_inner();
+        let decoded: Challenge<G> =
+            decode(&material, &encode(&material, &challenge).unwrap()).unwrap();
+        assert_eq!(
+            (decoded.auth_id, decoded.c),
+            (challenge.auth_id.clone(), challenge.c.clone())
+        );
+
+        let request = VerificationRequest::<G> {
+            auth_id: challenge.auth_id,
+            s: challenge.c,
+        };
+        let decoded: VerificationRequest<G> =
+            decode(&material, &encode(&material, &request).unwrap()).unwrap();
+        assert_eq!((decoded.auth_id, decoded.s), (request.auth_id, request.s));
+
+        let proof = nizk::prove(material.clone(), x, &mut Transcript::new(b"test"));
+        let decoded: Proof<G> = decode(&material, &encode(&material, &proof).unwrap()).unwrap();
+        assert_eq!(
+            (decoded.r1, decoded.r2, decoded.s),
+            (proof.r1, proof.r2, proof.s)
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        round_trip(known_material(), &BigInt::from(1234567890));
+        round_trip(Material::default(), &BigInt::from(1234567890));
+        let material = RistrettoMaterial::default();
+        round_trip(material.clone(), &material.scalar_from_u128(1234567890));
+
+        for material in [
+            known_material(),
+            Material::default(),
+            Material::generate(64, None).unwrap().with_h_seed("seed"),
+        ] {
+            let decoded = decode_material(&encode_material(&material).unwrap()).unwrap();
+            assert_eq!(decoded.encode_params(), material.encode_params());
+            assert_eq!(
+                (decoded.group, decoded.h_seed),
+                (material.group, material.h_seed)
+            );
+        }
+
+        // The last byte of `p`, made even.
+        let mut even = encode_material(&known_material()).unwrap();
+        even[12] ^= 1;
+        assert_eq!(
+            decode_material(&even).map(|_| ()),
+            Err(MaterialError::InvalidModulus("p").into())
+        );
+    }
+
+    /// Pins the encoding of a `Register` over the 64-bit material: the header, the number of bases, and the
+    /// fixed-length bases and public values.
+    #[test]
+    fn test_known_answer() {
+        let material = known_material();
+        let register = Register::new(material.clone(), &BigInt::from(1234567890));
+        assert_eq!(
+            hex(&encode(&material, &register).unwrap()),
+            concat!(
+                "0100020002",
+                "00000000000000040000000000000009",
+                "630c9d66ea14f66b2a1d6020ffe6ee06"
+            )
+        );
+    }
+
+    /// Test that values whose length does not fit in a `u16` are reported as errors instead of panicking.
+    #[test]
+    fn test_encode_failure() {
+        let material = known_material();
+        let long = "a".repeat(u16::MAX as usize + 1);
+        let challenge = Challenge::<Material> {
+            auth_id: long.clone().into(),
+            c: BigInt::from(1),
+        };
+        assert_eq!(
+            encode(&material, &challenge),
+            Err(ProtocolError::InvalidEncoding("auth_id"))
+        );
+        assert_eq!(
+            encode_material(&material.with_h_seed(&long)),
+            Err(ProtocolError::InvalidEncoding("h_seed"))
+        );
+    }
+
+    #[test]
+    fn test_decode_failure() {
+        let material = known_material();
+        let register = Register::new(material.clone(), &BigInt::from(42));
+        let bytes = encode(&material, &register).unwrap();
+        let decode_register = |bytes: &[u8]| decode::<_, Register>(&material, bytes).map(|_| ());
+        assert_eq!(decode_register(&bytes), Ok(()));
+
+        let mut other = bytes.clone();
+        other[0] = 2;
+        assert_eq!(
+            decode_register(&other),
+            Err(ProtocolError::UnsupportedVersion(2))
+        );
+        assert_eq!(
+            decode::<_, Register<_>>(&Material::default(), &bytes).map(|_| ()),
+            Err(ProtocolError::GroupMismatch {
+                expected: NamedGroup::Ffdhe2048.id(),
+                found: CUSTOM_GROUP_ID
+            })
+        );
+        assert_eq!(
+            decode::<_, ChaumPedersenCommitment>(&material, &bytes).map(|_| ()),
+            Err(ProtocolError::InvalidEncoding("unexpected message type"))
+        );
+        assert_eq!(
+            decode_register(&bytes[..bytes.len() - 1]),
+            Err(ProtocolError::InvalidEncoding("the message is truncated"))
+        );
+        assert_eq!(
+            decode_register(&[bytes.as_slice(), &[0]].concat()),
+            Err(ProtocolError::InvalidEncoding("trailing bytes"))
+        );
+        let mut no_bases = bytes.clone();
+        no_bases[3..5].fill(0);
+        assert_eq!(
+            decode_register(&no_bases),
+            Err(ProtocolError::InvalidEncoding("bases"))
+        );
+        let mut non_canonical = bytes;
+        non_canonical[21..29].fill(0xff);
+        assert_eq!(
+            decode_register(&non_canonical),
+            Err(ProtocolError::InvalidEncoding("ys"))
+        );
+    }
+}
@@ -0,0 +1,71 @@
+//! This module contains the errors returned by the protocol.
+use thiserror::Error;
+
+/// Reasons why a `Material` is rejected by `Material::validate`.
+#[derive(Debug, Clone, Eq, Hash, PartialEq, Error)]
+pub enum MaterialError {
+    #[error("p has more than {} bits", crate::protocol::ct::MAX_BITS)]
+    PTooLarge,
+    #[error("p is not prime")]
+    PNotPrime,
+    #[error("q is not prime")]
+    QNotPrime,
+    #[error("q is not (p - 1) / 2")]
+    QMismatch,
+    #[error("{0} is not a positive odd number")]
+    InvalidModulus(&'static str),
+    #[error("{0} is not a non-trivial element of order q")]
+    InvalidGenerator(&'static str),
+    #[error("g and h must be different")]
+    EqualGenerators,
+}
+
+/// Reasons why the values received for a verification are rejected before checking the proof.
+#[derive(Debug, Clone, Eq, Hash, PartialEq, Error)]
+pub enum RejectionReason {
+    #[error("{0} is not a non-trivial element of the subgroup of order q")]
+    NotInGroup(&'static str),
+    #[error("{0} is not in the range [0, q)")]
+    OutOfRange(&'static str),
+    #[error("{0} does not have one value per statement")]
+    WrongLength(&'static str),
+    #[error("there are no bases")]
+    NoBases,
+}
+
+/// Errors returned by the fallible functions of the protocol.
+#[derive(Debug, Clone, Eq, PartialEq, Error)]
+pub enum ProtocolError {
+    #[error("{0} is missing")]
+    MissingParameter(&'static str),
+    #[error("{0} is not a valid hexadecimal number")]
+    InvalidHex(&'static str),
+    #[error("invalid parameter: {0}")]
+    InvalidParameter(&'static str),
+    #[error("p must have between {min} and {max} bits, not {actual}")]
+    UnsupportedBits {
+        min: usize,
+        max: usize,
+        actual: usize,
+    },
+    #[error("the {name} has {actual} bytes, fewer than the minimum of {min}")]
+    TooShort {
+        name: &'static str,
+        min: usize,
+        actual: usize,
+    },
+    #[error("the fingerprint does not match the parameters")]
+    FingerprintMismatch,
+    #[error("invalid material: {0}")]
+    InvalidMaterial(#[from] MaterialError),
+    #[error("verification rejected: {0}")]
+    Rejected(#[from] RejectionReason),
+    #[error("verification failed")]
+    VerificationFailed,
+    #[error("version {0} of the encoding is not supported")]
+    UnsupportedVersion(u8),
+    #[error("the message belongs to group {found} instead of group {expected}")]
+    GroupMismatch { expected: u8, found: u8 },
+    #[error("invalid encoding: {0}")]
+    InvalidEncoding(&'static str),
+}
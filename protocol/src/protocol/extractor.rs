@@ -0,0 +1,111 @@
+//! This module contains the special-soundness extractor of the Chaum-Pedersen ZK protocol.
+//!
+//! Two accepting transcripts with the same commitments `r1` and `r2` but different challenges `c1` and `c2` satisfy
+//! `g^s1 * y1^c1 = g^s2 * y1^c2`, so `y1 = g^x` with:
+//!
+//! `x = (s2 - s1) / (c1 - c2)`
+//!
+//! This is why the protocol is sound: a prover able to answer two challenges for the same commitment knows `x`. It is
+//! also why the nonce `k` must never be reused: two logins with the same commitment hand `x` to anyone who sees them.
+//! The extractor is used by the tests as a soundness regression test, and shows what a verifier receiving the same
+//! commitment twice could learn.
+use crate::protocol::cp::{ProtocolState, Verification, VerificationResult};
+use crate::protocol::group::Group;
+
+/// Extracts the secret `x` from two accepting transcripts sharing their commitments.
+///
+/// # Arguments
+///
+/// * `first`, `second` - The transcripts, with the same material, `y1`, `y2`, `r1` and `r2`.
+///
+/// # Returns
+///
+/// The secret `x` such that `y1 = g^x` and `y2 = h^x`, or `None` if the transcripts do not share their public values
+/// and commitments, have the same challenge, or any of them does not verify.
+pub fn extract<G: Group>(first: &Verification<G>, second: &Verification<G>) -> Option<G::Scalar> {
+    let material = &first.material;
+    if material.encode_params() != second.material.encode_params()
+        || first.bases != second.bases
+        || first.ys != second.ys
+        || first.rs != second.rs
+    {
+        return None;
+    }
+    let c_difference = material.scalar_sub(&first.c, &second.c);
+    let c_difference_inverse = material.scalar_invert(&c_difference)?;
+    for transcript in [first, second] {
+        let result = ProtocolState::from(transcript.clone())
+            .change()
+            .into_inner();
+        if result != VerificationResult::ChallengeVerifiedSuccess {
+            return None;
+        }
+    }
+    let s_difference = material.scalar_sub(&second.s, &first.s);
+    Some(material.scalar_mul(&s_difference, &c_difference_inverse))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::cp::{
+        Challenge, ChallengeResponse, Material, ProtocolTransition, Register,
+    };
+    use crate::protocol::ristretto::RistrettoMaterial;
+    use crate::protocol::secret::Secret;
+    use num_bigint::BigInt;
+
+    /// Answers two challenges for the same commitment, as a prover reusing its nonce would.
+    fn reused_nonce<G: Group>(material: &G, x: &G::Scalar) -> [Verification<G>; 2] {
+        let register = Register::new(material.clone(), x);
+        let commitment = ProtocolState::from(register.clone()).change().into_inner();
+        [(); 2].map(|_| {
+            let challenge: Challenge<G> =
+                ProtocolState::from(material.clone()).change().into_inner();
+            let response = ProtocolState::from(
+                ChallengeResponse::builder()
+                    .challenge(challenge.clone())
+                    .material(material.clone())
+                    .x(x.clone())
+                    .k(Secret::new(commitment.k.expose().clone()))
+                    .build(),
+            )
+            .change()
+            .into_inner();
+            Verification::builder()
+                .material(material.clone())
+                .ys(register.ys.clone())
+                .rs(commitment.rs.clone())
+                .c(challenge.c)
+                .s(response.s)
+                .build()
+        })
+    }
+
+    #[test]
+    fn test_extract() {
+        let material = Material::generate(256, None).unwrap();
+        let x = BigInt::from(123_456_789);
+        let [first, second] = reused_nonce(&material, &x);
+        assert_eq!(extract(&first, &second), Some(x));
+
+        let material = RistrettoMaterial::default();
+        let x = material.random_scalar(&mut rand::thread_rng());
+        let [first, second] = reused_nonce(&material, &x);
+        assert_eq!(extract(&first, &second), Some(x));
+    }
+
+    #[test]
+    fn test_extract_failure() {
+        let material = Material::generate(256, None).unwrap();
+        let [first, second] = reused_nonce(&material, &BigInt::from(42));
+        assert_eq!(extract(&first, &first), None);
+        let forged = Verification {
+            s: material.scalar_add(&second.s, &1.into()),
+            ..second.clone()
+        };
+        assert_eq!(extract(&first, &forged), None);
+        let [other, _] = reused_nonce(&material, &BigInt::from(42));
+        assert_eq!(extract(&first, &other), None);
+    }
+}
@@ -0,0 +1,125 @@
+//! This module contains the `Group` abstraction the Chaum-Pedersen protocol is defined over.
+//!
+//! A `Group` is a prime-order group together with the two generators `g` and `h` of the protocol. It provides the
+//! operations on its elements (exponentiation, multiplication and identity), the arithmetic on its scalars (the
+//! integers modulo the group order), and a canonical byte encoding for both. The operations involving the secrets of
+//! the prover have separate `_secret` variants, which backends must implement in constant time.
+//!
+//! The protocol steps in `cp` are generic over any `Group`, so new backends can be plugged in by implementing this
+//! trait (and `ProtocolStep`) for the type holding the public parameters. The crate provides two implementations:
+//! - `Material`: The subgroup of order `q` of the integers modulo a safe prime `p = 2q + 1`.
+//! - `RistrettoMaterial`: The Ristretto255 group.
+use crate::protocol::cp::ProtocolStep;
+use crate::protocol::secret::Zeroizable;
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+use std::fmt::Debug;
+
+/// Domain separation tag of the fingerprint of the public parameters.
+const FINGERPRINT_DST: &[u8] = b"zk-cp-protocol/fingerprint/sha256/v1";
+
+/// Prime-order group with the generators `g` and `h` used by the protocol.
+///
+/// The group is written multiplicatively: `exp(b, e)` is `b^e` and `mul(a, b)` is `a * b`.
+pub trait Group: ProtocolStep + Clone + Debug {
+    /// Element of the group.
+    type Element: Clone + Debug + PartialEq;
+    /// Integer modulo the order of the group. Secret scalars are held in a `Secret`, which zeroizes them on drop.
+    type Scalar: Clone + Debug + PartialEq + Zeroizable;
+
+    /// Returns the generator `g`.
+    fn g(&self) -> &Self::Element;
+
+    /// Returns the generator `h`, whose discrete logarithm relative to `g` must be unknown.
+    fn h(&self) -> &Self::Element;
+
+    /// Returns the identity element.
+    fn identity(&self) -> Self::Element;
+
+    /// Computes `base^exponent`.
+    fn exp(&self, base: &Self::Element, exponent: &Self::Scalar) -> Self::Element;
+
+    /// Computes `a * b`.
+    fn mul(&self, a: &Self::Element, b: &Self::Element) -> Self::Element;
+
+    /// Computes `base^exponent` for a secret exponent, in time independent of its value.
+    fn exp_secret(&self, base: &Self::Element, exponent: &Self::Scalar) -> Self::Element;
+
+    /// Checks whether two elements are equal, in time independent of their values.
+    fn element_ct_eq(&self, a: &Self::Element, b: &Self::Element) -> bool;
+
+    /// Checks that `e` is an element of the prime-order group other than the identity.
+    fn is_element(&self, e: &Self::Element) -> bool;
+
+    /// Checks that `s` is reduced modulo the order of the group.
+    fn is_scalar(&self, s: &Self::Scalar) -> bool;
+
+    /// Draws a uniformly random non-zero scalar.
+    fn random_scalar<R: RngCore + CryptoRng>(&self, rng: &mut R) -> Self::Scalar;
+
+    /// Computes `a + b` modulo the order of the group.
+    fn scalar_add(&self, a: &Self::Scalar, b: &Self::Scalar) -> Self::Scalar;
+
+    /// Computes `a - b` modulo the order of the group.
+    fn scalar_sub(&self, a: &Self::Scalar, b: &Self::Scalar) -> Self::Scalar;
+
+    /// Computes `a * b` modulo the order of the group.
+    fn scalar_mul(&self, a: &Self::Scalar, b: &Self::Scalar) -> Self::Scalar;
+
+    /// Computes the inverse of `a` modulo the order of the group, or `None` if `a` is zero.
+    fn scalar_invert(&self, a: &Self::Scalar) -> Option<Self::Scalar>;
+
+    /// Computes `k - c * x` modulo the order of the group for secret `k` and `x`, in time independent of their values.
+    fn scalar_mul_sub_secret(
+        &self,
+        k: &Self::Scalar,
+        c: &Self::Scalar,
+        x: &Self::Scalar,
+    ) -> Self::Scalar;
+
+    /// Converts a small integer to a scalar, reducing it modulo the order of the group.
+    fn scalar_from_u128(&self, n: u128) -> Self::Scalar;
+
+    /// Computes the product of `base^exponent` over all the terms.
+    ///
+    /// The default implementation exponentiates every term separately. Backends should override it with a
+    /// multi-exponentiation algorithm sharing the work between the terms.
+    fn multi_exp(&self, terms: &[(&Self::Element, &Self::Scalar)]) -> Self::Element {
+        terms.iter().fold(self.identity(), |acc, (base, exponent)| {
+            self.mul(&acc, &self.exp(base, exponent))
+        })
+    }
+
+    /// Encodes an element with a fixed length.
+    fn encode_element(&self, e: &Self::Element) -> Vec<u8>;
+
+    /// Decodes an element. Returns `None` if the bytes are not a canonical encoding.
+    fn decode_element(&self, bytes: &[u8]) -> Option<Self::Element>;
+
+    /// Encodes a scalar with a fixed length.
+    fn encode_scalar(&self, s: &Self::Scalar) -> Vec<u8>;
+
+    /// Decodes a scalar. Returns `None` if the bytes are not a canonical encoding.
+    fn decode_scalar(&self, bytes: &[u8]) -> Option<Self::Scalar>;
+
+    /// Returns the identifier of the group in the headers of the binary encoding of `encoding`.
+    fn group_id(&self) -> u8;
+
+    /// Encodes the public parameters of the group, so that they can be bound into hashes.
+    fn encode_params(&self) -> Vec<u8>;
+
+    /// Returns a stable fingerprint of the public parameters, the SHA-256 hash of `encode_params`.
+    ///
+    /// Two parties hold the same parameters if and only if their fingerprints match, so exchanging it detects a
+    /// mismatch before any proof is run.
+    fn fingerprint(&self) -> [u8; 32] {
+        Sha256::new()
+            .chain_update(FINGERPRINT_DST)
+            .chain_update(self.encode_params())
+            .finalize()
+            .into()
+    }
+
+    /// Hashes `input` to a scalar which is statistically close to uniform modulo the order of the group.
+    fn hash_to_scalar(&self, input: &[u8]) -> Self::Scalar;
+}
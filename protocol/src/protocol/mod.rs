@@ -1,2 +1,47 @@
+/// Batch verification of many Chaum-Pedersen proofs
+pub mod batch;
+/// AND and OR compositions of sigma protocols
+pub mod compose;
 /// Chaum-Pedersen ZK Protocol
 pub mod cp;
+/// Constant-time modular arithmetic for secret values
+pub mod ct;
+/// Hierarchical derivation of unlinkable per-verifier secrets from a master secret
+pub mod derivation;
+/// Equality of discrete logarithms over any number of bases
+pub mod dleq;
+/// Versioned canonical binary encoding of the protocol messages
+pub mod encoding;
+/// Errors returned by the protocol
+pub mod error;
+/// Special-soundness extractor recovering the secret from two transcripts sharing a commitment
+pub mod extractor;
+/// Prime-order group abstraction the protocol is generic over
+pub mod group;
+/// Well-known RFC 3526 and RFC 7919 groups
+pub mod groups;
+/// Fixed-base tables and multi-exponentiation modulo p
+pub mod multiexp;
+/// Non-interactive Chaum-Pedersen proofs using the Fiat-Shamir transform
+pub mod nizk;
+/// Hedged derivation of the nonce of the prover
+pub mod nonce;
+/// Derivation of the secret of a user from a password with Argon2id
+pub mod password;
+/// Arbitrary-precision primality testing and safe prime generation
+pub mod primes;
+/// Chaum-Pedersen ZK Protocol over Ristretto255
+pub mod ristretto;
+/// Schnorr proof of knowledge of a discrete logarithm
+pub mod schnorr;
+/// Zeroizing wrapper for the secret values of the prover
+pub mod secret;
+/// Generic three-move proofs of knowledge
+pub mod sigma;
+/// Honest-verifier zero-knowledge simulator of Chaum-Pedersen transcripts
+pub mod simulator;
+/// Fixtures and helpers shared by the tests
+#[cfg(test)]
+mod test_support;
+/// Labelled transcript binding Fiat-Shamir challenges to their context
+pub mod transcript;
@@ -0,0 +1,237 @@
+//! This module contains the exponentiation algorithms modulo `p` used by `Material`.
+//!
+//! - `FixedBaseTable`: Precomputed powers `base^(2^(w * i))` of a fixed base, used to exponentiate it with Yao's
+//!   method without any squaring. `Material` keeps one table for `g` and one for `h`.
+//! - `straus`: Simultaneous exponentiation of several bases (Straus / Shamir's trick), where all the bases share a
+//!   single chain of squarings and each base is multiplied in with a window of bits at a time.
+//!
+//! Both algorithms use plain multiplications followed by a reduction modulo `p`. These are slower than the Montgomery
+//! multiplications of `BigInt::modpow`, so one or two variable bases are better exponentiated with `modpow`; the gains
+//! come from removing the squarings of fixed bases and from sharing them between many variable bases.
+use num_bigint::{BigInt, Sign};
+use num_traits::One;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, OnceLock};
+
+/// Precomputed table of a fixed base.
+#[derive(Clone)]
+pub struct FixedBaseTable {
+    base: BigInt,
+    modulus: BigInt,
+    window: u64,
+    /// `powers[i] = base^(2^(window * i)) mod modulus`
+    powers: Vec<BigInt>,
+}
+
+impl FixedBaseTable {
+    /// Precomputes the table of `base` modulo `modulus` for exponents of up to `exponent_bits` bits.
+    ///
+    /// The window is chosen to minimize the number of multiplications of an exponentiation, which is one per window of
+    /// the exponent plus `2^(window + 1)`.
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - The fixed base.
+    /// * `modulus` - The modulus.
+    /// * `exponent_bits` - The maximum bit length of the exponents.
+    pub fn new(base: &BigInt, modulus: &BigInt, exponent_bits: u64) -> Self {
+        let window = best_window(exponent_bits, |w| 1 << (w + 1));
+        let windows = exponent_bits.div_ceil(window).max(1);
+        let mut powers = Vec::with_capacity(windows as usize);
+        let mut power = base % modulus;
+        for _ in 0..windows {
+            let next = (0..window).fold(power.clone(), |acc, _| (&acc * &acc) % modulus);
+            powers.push(power);
+            power = next;
+        }
+        FixedBaseTable {
+            base: base.clone(),
+            modulus: modulus.clone(),
+            window,
+            powers,
+        }
+    }
+
+    /// Checks whether the table was built for the given base and modulus.
+    pub fn is_for(&self, base: &BigInt, modulus: &BigInt) -> bool {
+        &self.base == base && &self.modulus == modulus
+    }
+
+    /// Computes `base^exponent mod modulus` with Yao's method.
+    ///
+    /// # Returns
+    ///
+    /// The result, or `None` if the exponent is negative or too large for the table.
+    pub fn exp(&self, exponent: &BigInt) -> Option<BigInt> {
+        if exponent.sign() == Sign::Minus
+            || exponent.bits() > self.window * self.powers.len() as u64
+        {
+            return None;
+        }
+        // buckets[d] is the product of the powers whose window of the exponent is d.
+        let mut buckets: Vec<Option<BigInt>> = vec![None; 1 << self.window];
+        for (i, power) in self.powers.iter().enumerate() {
+            let digit = window_digit(exponent, i as u64 * self.window, self.window);
+            if digit != 0 {
+                buckets[digit] = Some(match &buckets[digit] {
+                    Some(bucket) => (bucket * power) % &self.modulus,
+                    None => power.clone(),
+                });
+            }
+        }
+        // result = prod_d buckets[d]^d, computed as a running product from the largest digit down.
+        let mut running: Option<BigInt> = None;
+        let mut result: Option<BigInt> = None;
+        for bucket in buckets.into_iter().skip(1).rev() {
+            if let Some(bucket) = bucket {
+                running = Some(match running {
+                    Some(running) => (running * bucket) % &self.modulus,
+                    None => bucket,
+                });
+            }
+            if let Some(running) = &running {
+                result = Some(match result {
+                    Some(result) => (result * running) % &self.modulus,
+                    None => running.clone(),
+                });
+            }
+        }
+        Some(result.unwrap_or_else(|| BigInt::one() % &self.modulus))
+    }
+}
+
+impl Debug for FixedBaseTable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FixedBaseTable")
+            .field("window", &self.window)
+            .field("powers", &self.powers.len())
+            .finish()
+    }
+}
+
+/// Lazily built fixed-base tables of `g` and `h`, shared between all the clones of a `Material`.
+#[derive(Clone, Default)]
+pub struct FixedBaseCache(Arc<OnceLock<[FixedBaseTable; 2]>>);
+
+impl FixedBaseCache {
+    /// Returns the tables, building them with `init` the first time.
+    pub fn get_or_init(&self, init: impl FnOnce() -> [FixedBaseTable; 2]) -> &[FixedBaseTable; 2] {
+        self.0.get_or_init(init)
+    }
+}
+
+impl Debug for FixedBaseCache {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("FixedBaseCache")
+            .field(&self.0.get().is_some())
+            .finish()
+    }
+}
+
+/// Computes the product of `base^exponent mod modulus` over all the terms with Straus' algorithm.
+///
+/// Every base gets a table of its first `2^w` powers, where the window `w` depends on the length of its exponent.
+/// The bits of the exponents are then scanned from the most significant one, squaring the accumulator once per bit
+/// and multiplying in the table entry of a base at the lowest bit of each of its windows.
+///
+/// # Arguments
+///
+/// * `terms` - The bases and their non-negative exponents.
+/// * `modulus` - The modulus.
+pub fn straus(terms: &[(&BigInt, &BigInt)], modulus: &BigInt) -> BigInt {
+    let one = BigInt::one() % modulus;
+    let tables: Vec<(u64, Vec<BigInt>)> = terms
+        .iter()
+        .map(|(base, exponent)| {
+            let window = best_window(exponent.bits(), |w| 1 << w);
+            let base = *base % modulus;
+            let mut table = Vec::with_capacity(1 << window);
+            table.push(one.clone());
+            for d in 1..(1 << window) {
+                table.push((&table[d - 1] * &base) % modulus);
+            }
+            (window, table)
+        })
+        .collect();
+    let top = terms.iter().map(|(_, e)| e.bits()).max().unwrap_or(0);
+    let mut acc: Option<BigInt> = None;
+    for bit in (0..top).rev() {
+        if let Some(a) = acc.take() {
+            acc = Some((&a * &a) % modulus);
+        }
+        for ((_, exponent), (window, table)) in terms.iter().zip(&tables) {
+            if bit % window != 0 {
+                continue;
+            }
+            let digit = window_digit(exponent, bit, *window);
+            if digit != 0 {
+                acc = Some(match acc {
+                    Some(a) => (a * &table[digit]) % modulus,
+                    None => table[digit].clone(),
+                });
+            }
+        }
+    }
+    acc.unwrap_or(one)
+}
+
+/// Returns the `window` bits of `n` starting at bit `start`.
+fn window_digit(n: &BigInt, start: u64, window: u64) -> usize {
+    (0..window)
+        .filter(|i| n.bit(start + i))
+        .fold(0, |digit, i| digit | (1 << i))
+}
+
+/// Returns the window width in `[1, 12]` minimizing `ceil(bits / w) + table_cost(w)`.
+fn best_window(bits: u64, table_cost: impl Fn(u64) -> u64) -> u64 {
+    (1..=12)
+        .min_by_key(|w| bits.div_ceil(*w) + table_cost(*w))
+        .expect("the range of windows is not empty")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::RandBigInt;
+
+    #[test]
+    fn test_fixed_base_table() {
+        let p = BigInt::from(2_305_843_009_213_693_951u64);
+        let base = BigInt::from(7);
+        let table = FixedBaseTable::new(&base, &p, 61);
+        assert!(table.is_for(&base, &p));
+        assert!(!table.is_for(&BigInt::from(3), &p));
+        let mut rng = rand::thread_rng();
+        for e in [BigInt::from(0), BigInt::from(1), BigInt::from(2), &p - 2]
+            .into_iter()
+            .chain((0..50).map(|_| rng.gen_bigint_range(&BigInt::from(0), &p)))
+        {
+            assert_eq!(table.exp(&e), Some(base.modpow(&e, &p)), "e = {}", e);
+        }
+        assert_eq!(table.exp(&(BigInt::one() << 128)), None);
+        assert_eq!(table.exp(&BigInt::from(-1)), None);
+    }
+
+    #[test]
+    fn test_straus() {
+        let p = BigInt::from(2_305_843_009_213_693_951u64);
+        let mut rng = rand::thread_rng();
+        assert_eq!(straus(&[], &p), BigInt::one());
+        for n in 1..8 {
+            let terms: Vec<(BigInt, BigInt)> = (0..n)
+                .map(|i| {
+                    let bits = if i % 2 == 0 { 61 } else { 16 };
+                    (
+                        rng.gen_bigint_range(&BigInt::from(2), &p),
+                        BigInt::from(rng.gen_biguint(bits)),
+                    )
+                })
+                .collect();
+            let expected = terms
+                .iter()
+                .fold(BigInt::one(), |acc, (b, e)| (acc * b.modpow(e, &p)) % &p);
+            let terms: Vec<(&BigInt, &BigInt)> = terms.iter().map(|(b, e)| (b, e)).collect();
+            assert_eq!(straus(&terms, &p), expected);
+        }
+    }
+}
@@ -0,0 +1,325 @@
+//! This module contains the non-interactive version of the Chaum-Pedersen ZK protocol.
+//!
+//! The verifier-issued challenge is replaced by the Fiat-Shamir transform: the prover computes the challenge itself
+//! from a `Transcript` as `c = H(transcript ‖ material ‖ y1 ‖ y2 ‖ r1 ‖ r2)` and emits a self-contained
+//! `Proof { r1, r2, s }`. Anyone knowing the material, `y1` and `y2` can check the proof without talking to the prover.
+//!
+//! - `prove`: Builds a `Proof` of knowledge of `x` such that `y1 = g^x` and `y2 = h^x`.
+//! - `verify`: Recomputes the challenge and runs the same checks as the interactive `Verification`.
+//!
+//! The transcript carries the protocol label, the user, the `AuthId` and any application context the proof is bound to,
+//! so a proof produced for one verifier, session or purpose is not valid for another. The prover and the verifier must
+//! build the transcript with the same messages.
+use crate::protocol::cp::{Material, ProtocolState, Register, Verification, VerificationResult};
+use crate::protocol::group::Group;
+use crate::protocol::transcript::Transcript;
+use rand::{CryptoRng, RngCore};
+use typed_builder::TypedBuilder;
+
+/// Domain separation tag of the Fiat-Shamir challenge.
+const FIAT_SHAMIR_DST: &[u8] = b"zk-cp-protocol/chaum-pedersen/fiat-shamir/v1";
+
+/// Non-interactive proof of equality of discrete logarithms.
+#[derive(Debug, Clone, TypedBuilder)]
+pub struct Proof<G: Group = Material> {
+    pub r1: G::Element,
+    pub r2: G::Element,
+    pub s: G::Scalar,
+}
+
+impl<G: Group> Proof<G> {
+    /// Encodes the proof as the fixed-length encodings of `r1`, `r2` and `s`.
+    ///
+    /// # Arguments
+    ///
+    /// * `material` - The group the proof belongs to.
+    pub fn encode(&self, material: &G) -> Vec<u8> {
+        [
+            material.encode_element(&self.r1),
+            material.encode_element(&self.r2),
+            material.encode_scalar(&self.s),
+        ]
+        .concat()
+    }
+
+    /// Decodes a proof encoded with `Proof::encode`.
+    ///
+    /// # Arguments
+    ///
+    /// * `material` - The group the proof belongs to.
+    /// * `bytes` - The encoded proof.
+    ///
+    /// # Returns
+    ///
+    /// The decoded proof, or `None` if the bytes are not a canonical encoding.
+    pub fn decode(material: &G, bytes: &[u8]) -> Option<Self> {
+        let element_len = material.encode_element(&material.identity()).len();
+        if bytes.len() < 2 * element_len {
+            return None;
+        }
+        let (r1, rest) = bytes.split_at(element_len);
+        let (r2, s) = rest.split_at(element_len);
+        Some(Proof {
+            r1: material.decode_element(r1)?,
+            r2: material.decode_element(r2)?,
+            s: material.decode_scalar(s)?,
+        })
+    }
+}
+
+/// Computes the Fiat-Shamir challenge by absorbing the material, `y1`, `y2`, `r1` and `r2` into the transcript.
+///
+/// # Arguments
+///
+/// * `material` - The group the proof runs on.
+/// * `y1`, `y2` - The public values `g^x` and `h^x`.
+/// * `r1`, `r2` - The commitments `g^k` and `h^k`.
+/// * `transcript` - The transcript holding the context the proof is bound to.
+pub fn challenge<G: Group>(
+    material: &G,
+    y1: &G::Element,
+    y2: &G::Element,
+    r1: &G::Element,
+    r2: &G::Element,
+    transcript: &mut Transcript,
+) -> G::Scalar {
+    transcript.append_message(b"dom-sep", FIAT_SHAMIR_DST);
+    transcript.append_material(material);
+    transcript.append_element(b"y1", material, y1);
+    transcript.append_element(b"y2", material, y2);
+    transcript.append_element(b"r1", material, r1);
+    transcript.append_element(b"r2", material, r2);
+    transcript.challenge_scalar(b"c", material)
+}
+
+/// Proves knowledge of `x` such that `y1 = g^x` and `y2 = h^x`, without any interaction with the verifier.
+///
+/// The nonce `k` is hedged: it is derived from `x`, the transcript and fresh randomness.
+///
+/// # Arguments
+///
+/// * `material` - The group the proof runs on.
+/// * `x` - The secret.
+/// * `transcript` - The transcript holding the context the proof is bound to.
+pub fn prove<G: Group>(material: G, x: &G::Scalar, transcript: &mut Transcript) -> Proof<G> {
+    prove_with_rng(material, x, transcript, &mut rand::thread_rng())
+}
+
+/// Same as `prove`, drawing the fresh randomness of the nonce from the given RNG.
+pub fn prove_with_rng<G: Group, R: RngCore + CryptoRng>(
+    material: G,
+    x: &G::Scalar,
+    transcript: &mut Transcript,
+    rng: &mut R,
+) -> Proof<G> {
+    let register = Register::new(material, x);
+    let commitment = ProtocolState::from(register.clone())
+        .change_hedged_with_rng(x, transcript, rng)
+        .into_inner();
+    let material = &commitment.material;
+    let c = challenge(
+        material,
+        register.y1(),
+        register.y2(),
+        commitment.r1(),
+        commitment.r2(),
+        transcript,
+    );
+    let s = material.scalar_mul_sub_secret(commitment.k.expose(), &c, x);
+    Proof {
+        r1: commitment.r1().clone(),
+        r2: commitment.r2().clone(),
+        s,
+    }
+}
+
+/// Verifies a proof produced by `prove`.
+///
+/// # Arguments
+///
+/// * `material` - The group the proof runs on.
+/// * `y1`, `y2` - The public values the prover registered.
+/// * `proof` - The proof.
+/// * `transcript` - The transcript holding the context the proof must be bound to.
+///
+/// # Returns
+///
+/// The `VerificationResult` of checking the proof against the recomputed challenge.
+pub fn verify<G: Group>(
+    material: G,
+    y1: G::Element,
+    y2: G::Element,
+    proof: Proof<G>,
+    transcript: &mut Transcript,
+) -> VerificationResult {
+    let c = challenge(&material, &y1, &y2, &proof.r1, &proof.r2, transcript);
+    ProtocolState::from(
+        Verification::builder()
+            .material(material)
+            .ys(vec![y1, y2])
+            .rs(vec![proof.r1, proof.r2])
+            .c(c)
+            .s(proof.s)
+            .build(),
+    )
+    .change()
+    .into_inner()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::cp::AuthId;
+    use crate::protocol::ristretto::RistrettoMaterial;
+    use crate::protocol::test_support::hex;
+    use curve25519_dalek::scalar::Scalar;
+    use num_bigint::BigInt;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    fn transcript(user: &str, auth_id: &str) -> Transcript {
+        let mut transcript = Transcript::new(b"test/login");
+        transcript.append_user(user);
+        transcript.append_auth_id(&AuthId::from(auth_id));
+        transcript.append_context(b"verifier-1");
+        transcript
+    }
+
+    #[test]
+    fn test_prove_verify() {
+        let material = Material::default();
+        let x = BigInt::from(42);
+        let register = Register::new(material.clone(), &x);
+        let proof = prove(material.clone(), &x, &mut transcript("alice", "1"));
+        assert_eq!(
+            verify(
+                material,
+                register.y1().clone(),
+                register.y2().clone(),
+                proof,
+                &mut transcript("alice", "1")
+            ),
+            VerificationResult::ChallengeVerifiedSuccess
+        );
+    }
+
+    #[test]
+    fn test_verify_failure() {
+        let material = Material::default();
+        let x = BigInt::from(42);
+        let register = Register::new(material.clone(), &x);
+        let proof = prove(material.clone(), &x, &mut transcript("alice", "1"));
+        for mut other in [
+            transcript("bob", "1"),
+            transcript("alice", "2"),
+            Transcript::new(b"test/login"),
+        ] {
+            assert_eq!(
+                verify(
+                    material.clone(),
+                    register.y1().clone(),
+                    register.y2().clone(),
+                    proof.clone(),
+                    &mut other
+                ),
+                VerificationResult::ChallengeVerificationFailed
+            );
+        }
+
+        let other = Register::new(material.clone(), &BigInt::from(43));
+        assert_eq!(
+            verify(
+                material.clone(),
+                other.y1().clone(),
+                other.y2().clone(),
+                proof.clone(),
+                &mut transcript("alice", "1")
+            ),
+            VerificationResult::ChallengeVerificationFailed
+        );
+
+        let tampered = Proof {
+            s: material.scalar_sub(&proof.s, &BigInt::from(1)),
+            ..proof
+        };
+        assert_eq!(
+            verify(
+                material,
+                register.y1().clone(),
+                register.y2().clone(),
+                tampered,
+                &mut transcript("alice", "1")
+            ),
+            VerificationResult::ChallengeVerificationFailed
+        );
+    }
+
+    #[test]
+    fn test_prove_verify_ristretto() {
+        let material = RistrettoMaterial::default();
+        let x = material.random_scalar(&mut rand::thread_rng());
+        let register = Register::new(material.clone(), &x);
+        let proof = prove(material.clone(), &x, &mut transcript("alice", "1"));
+        let bytes = proof.encode(&material);
+        assert_eq!(bytes.len(), 96);
+        let decoded = Proof::decode(&material, &bytes).unwrap();
+        assert_eq!(
+            verify(
+                material,
+                *register.y1(),
+                *register.y2(),
+                decoded,
+                &mut transcript("alice", "1")
+            ),
+            VerificationResult::ChallengeVerifiedSuccess
+        );
+    }
+
+    #[test]
+    fn test_encoding() {
+        let material = Material::default();
+        let proof = prove(
+            material.clone(),
+            &BigInt::from(42),
+            &mut Transcript::new(b"test"),
+        );
+        let bytes = proof.encode(&material);
+        assert_eq!(bytes.len(), 3 * 256);
+        let decoded = Proof::decode(&material, &bytes).unwrap();
+        assert_eq!(decoded.r1, proof.r1);
+        assert_eq!(decoded.r2, proof.r2);
+        assert_eq!(decoded.s, proof.s);
+        assert!(Proof::decode(&material, &bytes[1..]).is_none());
+    }
+
+    /// Known-answer test pinning a proof with a hedged nonce, for a fixed secret, transcript and seeded RNG.
+    #[test]
+    fn test_known_answer() {
+        let material = RistrettoMaterial::default();
+        let x = Scalar::from(1_234_567_890u64);
+        let mut rng = ChaCha20Rng::seed_from_u64(2024);
+        let proof = prove_with_rng(
+            material.clone(),
+            &x,
+            &mut transcript("alice", "1"),
+            &mut rng,
+        );
+        assert_eq!(
+            hex(&proof.encode(&material)),
+            "1edabd46fa08737c9cee3d14faa575f934647d9202a7c773f0d96e20a9859200\
+             9eb2450a8963f4aa90d287c2f7496fcabdef0f0e9c33d9482c7283d0ab499714\
+             8d51cd65d0d3f1e59983bd0217bf8aa7376cbed0c2300ccfe989f2d22ec56209"
+        );
+        let register = Register::new(material.clone(), &x);
+        assert_eq!(
+            verify(
+                material,
+                *register.y1(),
+                *register.y2(),
+                proof,
+                &mut transcript("alice", "1")
+            ),
+            VerificationResult::ChallengeVerifiedSuccess
+        );
+    }
+}
@@ -0,0 +1,219 @@
+//! This module contains the hedged derivation of the nonce `k` of the prover.
+//!
+//! The response `s = k - c * x` leaks `x` as soon as `k` is known, reused or biased: two responses `s1`, `s2` to the
+//! challenges `c1`, `c2` with the same `k` give `x = (s1 - s2) / (c2 - c1)`, and even a few biased bits of many nonces
+//! are enough for lattice attacks. Drawing `k` from the system RNG alone puts the secret at the mercy of that RNG.
+//!
+//! Following RFC 6979 and its hedged variant, `k` is instead drawn from an HMAC-SHA512 DRBG seeded with:
+//! - The secret `x`.
+//! - The public parameters of the group.
+//! - The digest of the `Transcript` the proof is bound to.
+//! - 32 bytes of fresh randomness.
+//!
+//! With a working RNG the nonce is as unpredictable as a random one, and the fresh randomness keeps two runs over the
+//! same transcript from producing the same `k`, which protects against fault attacks on deterministic nonces.
+//!
+//! With a broken RNG the nonce is still unique per secret and transcript, as in RFC 6979, but that only protects `x` if
+//! the transcript is unique per proof. In the non-interactive proofs of `nizk` the challenge is derived from the
+//! commitment, so the same `k` always comes with the same challenge and response. In the interactive protocol the
+//! commitment is sent before the verifier draws its challenge, so a transcript without any per-session input gives the
+//! same `k` on every login, and the answers to two different challenges reveal `x` (see `extractor`). Interactive
+//! provers must therefore absorb a value unique to the session with `Transcript::append_session`, such as a persisted
+//! counter or the current time, which does not come from the RNG. Should that value repeat as well, the only remaining
+//! protection is the verifier refusing commitments it has already seen.
+use crate::protocol::group::Group;
+use crate::protocol::transcript::Transcript;
+use hmac::{Hmac, Mac};
+use rand::{CryptoRng, RngCore};
+use sha2::Sha512;
+use zeroize::Zeroize;
+
+/// Domain separation tag of the nonce derivation.
+const NONCE_DST: &[u8] = b"zk-cp-protocol/nonce/hmac-sha512/v1";
+
+/// Length of the output of HMAC-SHA512.
+const HMAC_LEN: usize = 64;
+
+/// Derives the nonce `k` of the prover from the secret, the transcript and fresh randomness.
+///
+/// # Arguments
+///
+/// * `material` - The group the proof runs on.
+/// * `x` - The secret of the prover.
+/// * `transcript` - The transcript holding the context the proof is bound to.
+/// * `rng` - The source of the fresh randomness.
+///
+/// # Returns
+///
+/// A non-zero scalar, drawn from the DRBG with `Group::random_scalar`.
+pub fn hedged_nonce<G: Group, R: RngCore + CryptoRng>(
+    material: &G,
+    x: &G::Scalar,
+    transcript: &Transcript,
+    rng: &mut R,
+) -> G::Scalar {
+    let mut entropy = [0u8; 32];
+    rng.fill_bytes(&mut entropy);
+    let mut x_bytes = material.encode_scalar(x);
+    let mut drbg = NonceRng::new(&[
+        NONCE_DST,
+        &x_bytes,
+        &material.encode_params(),
+        &transcript.digest(),
+        &entropy,
+    ]);
+    x_bytes.zeroize();
+    entropy.zeroize();
+    material.random_scalar(&mut drbg)
+}
+
+/// HMAC-SHA512 DRBG, as used by RFC 6979 to generate the nonce.
+///
+/// The state is zeroized on drop, since it is derived from the secret.
+pub struct NonceRng {
+    key: [u8; HMAC_LEN],
+    v: [u8; HMAC_LEN],
+}
+
+impl NonceRng {
+    /// Seeds the DRBG as in steps b to g of RFC 6979, section 3.2.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The seed material. Every part is prefixed with its length, so different splits of the same bytes
+    ///   seed different DRBGs.
+    pub fn new(seed: &[&[u8]]) -> Self {
+        let mut drbg = NonceRng {
+            key: [0x00; HMAC_LEN],
+            v: [0x01; HMAC_LEN],
+        };
+        drbg.update(0x00, seed);
+        drbg.update(0x01, seed);
+        drbg
+    }
+
+    /// Computes `K = HMAC_K(V || tag || seed)` and `V = HMAC_K(V)`.
+    fn update(&mut self, tag: u8, seed: &[&[u8]]) {
+        let mut mac = self.mac();
+        mac.update(&self.v);
+        mac.update(&[tag]);
+        for part in seed {
+            mac.update(&(part.len() as u64).to_be_bytes());
+            mac.update(part);
+        }
+        self.key.copy_from_slice(&mac.finalize().into_bytes());
+        self.next_v();
+    }
+
+    /// Computes `V = HMAC_K(V)`.
+    fn next_v(&mut self) {
+        let mut mac = self.mac();
+        mac.update(&self.v);
+        self.v.copy_from_slice(&mac.finalize().into_bytes());
+    }
+
+    fn mac(&self) -> Hmac<Sha512> {
+        Hmac::<Sha512>::new_from_slice(&self.key).expect("HMAC accepts keys of any length")
+    }
+}
+
+impl RngCore for NonceRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+        u64::from_le_bytes(bytes)
+    }
+
+    /// Generates the bytes as in step h.2 of RFC 6979, and updates the state as in step h.3 afterwards, so a candidate
+    /// rejected by `Group::random_scalar` is followed by a fresh one.
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(HMAC_LEN) {
+            self.next_v();
+            chunk.copy_from_slice(&self.v[..chunk.len()]);
+        }
+        self.update(0x00, &[]);
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl CryptoRng for NonceRng {}
+
+impl Drop for NonceRng {
+    fn drop(&mut self) {
+        self.key.zeroize();
+        self.v.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::cp::Material;
+    use crate::protocol::ristretto::RistrettoMaterial;
+    use num_bigint::BigInt;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    #[test]
+    fn test_nonce_rng() {
+        let output = |seed: &[&[u8]]| {
+            let mut drbg = NonceRng::new(seed);
+            let mut bytes = [0u8; 100];
+            drbg.fill_bytes(&mut bytes);
+            let next = drbg.next_u64();
+            (bytes, next)
+        };
+        let (bytes, next) = output(&[b"x", b"transcript"]);
+        assert_eq!((bytes, next), output(&[b"x", b"transcript"]));
+        assert_ne!(bytes[..8], next.to_le_bytes());
+        assert_ne!(bytes, output(&[b"y", b"transcript"]).0);
+        assert_ne!(bytes, output(&[b"x", b"other"]).0);
+        assert_ne!(bytes, output(&[b"xt", b"ranscript"]).0);
+    }
+
+    #[test]
+    fn test_hedged_nonce() {
+        let material = Material::default();
+        let x = BigInt::from(42);
+        let transcript = Transcript::new(b"test");
+        let mut rng = rand::thread_rng();
+        let k1 = hedged_nonce(&material, &x, &transcript, &mut rng);
+        let k2 = hedged_nonce(&material, &x, &transcript, &mut rng);
+        assert!(material.is_scalar(&k1) && k1 != BigInt::from(0));
+        assert_ne!(k1, k2);
+
+        let material = RistrettoMaterial::default();
+        let x = material.random_scalar(&mut rng);
+        let k1 = hedged_nonce(&material, &x, &transcript, &mut rng);
+        assert_ne!(k1, hedged_nonce(&material, &x, &transcript, &mut rng));
+    }
+
+    /// With an RNG stuck on the same output, only a value unique to the session keeps the nonces apart.
+    #[test]
+    fn test_hedged_nonce_stuck_rng() {
+        let material = Material::default();
+        let x = BigInt::from(42);
+        let nonce = |session: &[u8]| {
+            let mut transcript = Transcript::new(b"test");
+            transcript.append_session(session);
+            hedged_nonce(
+                &material,
+                &x,
+                &transcript,
+                &mut ChaCha20Rng::seed_from_u64(0),
+            )
+        };
+        assert_eq!(nonce(b"1"), nonce(b"1"));
+        assert_ne!(nonce(b"1"), nonce(b"2"));
+    }
+}
@@ -0,0 +1,188 @@
+//! This module contains the derivation of the secret `x` of a user from a password.
+//!
+//! A password is stretched with the memory-hard Argon2id KDF and a public per-user salt, so that an attacker holding
+//! the public values `y1` and `y2` of a user must pay the full cost of Argon2id for every password guess, and cannot
+//! attack several users at once. The output is then hashed into a scalar of the group with `Group::hash_to_scalar`,
+//! bound to the `Group::fingerprint` of the material, so the same password gives unrelated secrets in different
+//! groups.
+//!
+//! The salt is not secret: `generate_salt` draws it once per user, and it is stored next to the material of the user.
+//! The same password, salt, parameters and material always give the same `x`.
+use crate::protocol::error::ProtocolError;
+use crate::protocol::group::Group;
+use crate::protocol::secret::Secret;
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+/// Length in bytes of the salts drawn by `generate_salt`.
+pub const SALT_LEN: usize = 16;
+
+/// Minimum length in bytes of a salt.
+pub const MIN_SALT_LEN: usize = 8;
+
+/// Domain separation tag of the hash of the Argon2id output into a scalar.
+const PASSWORD_DST: &[u8] = b"zk-cp-protocol/password/argon2id/v1";
+
+/// Length in bytes of the Argon2id output.
+const OUTPUT_LEN: usize = 64;
+
+/// Draws a random salt for a new user.
+pub fn generate_salt<R: RngCore + CryptoRng>(rng: &mut R) -> [u8; SALT_LEN] {
+    let mut salt = [0; SALT_LEN];
+    rng.fill_bytes(&mut salt);
+    salt
+}
+
+/// Returns the default Argon2id parameters: 19 MiB of memory, 2 passes and 1 lane, as recommended by OWASP.
+pub fn default_params() -> Params {
+    Params::new(19 * 1024, 2, 1, Some(OUTPUT_LEN)).expect("the default parameters are valid")
+}
+
+/// Derives the secret `x` of a user from a password with the default parameters.
+///
+/// # Arguments
+///
+/// * `material` - The group `x` belongs to.
+/// * `password` - The password of the user.
+/// * `salt` - The public salt of the user, of at least `MIN_SALT_LEN` bytes.
+///
+/// # Returns
+///
+/// The secret `x`, or `ProtocolError::TooShort` if the salt is shorter than `MIN_SALT_LEN`.
+pub fn derive_x<G: Group>(
+    material: &G,
+    password: &[u8],
+    salt: &[u8],
+) -> Result<Secret<G::Scalar>, ProtocolError> {
+    derive_x_with_params(material, password, salt, default_params())
+}
+
+/// Same as `derive_x`, with the given Argon2id parameters. The output length of the parameters is ignored.
+pub fn derive_x_with_params<G: Group>(
+    material: &G,
+    password: &[u8],
+    salt: &[u8],
+    params: Params,
+) -> Result<Secret<G::Scalar>, ProtocolError> {
+    if salt.len() < MIN_SALT_LEN {
+        return Err(ProtocolError::TooShort {
+            name: "salt",
+            min: MIN_SALT_LEN,
+            actual: salt.len(),
+        });
+    }
+    let params = Params::new(
+        params.m_cost(),
+        params.t_cost(),
+        params.p_cost(),
+        Some(OUTPUT_LEN),
+    )
+    .map_err(|_| ProtocolError::InvalidParameter("invalid Argon2id parameters"))?;
+    let mut input = [PASSWORD_DST, &material.fingerprint(), &[0; OUTPUT_LEN]].concat();
+    let prefix = input.len() - OUTPUT_LEN;
+    let x = Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password, salt, &mut input[prefix..])
+        .map(|_| Secret::new(material.hash_to_scalar(&input)))
+        .map_err(|_| ProtocolError::InvalidParameter("invalid Argon2id input"));
+    input.zeroize();
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::cp::{
+        ChaumPedersen, ChaumPedersenStatement, Material, VerificationResult,
+    };
+    use crate::protocol::ristretto::RistrettoMaterial;
+    use crate::protocol::sigma::SigmaProtocol;
+    use crate::protocol::test_support::known_material;
+    use num_bigint::BigInt;
+
+    /// Cheap parameters, so that the tests run quickly.
+    fn params() -> Params {
+        Params::new(64, 1, 1, None).unwrap()
+    }
+
+    /// Derives `x` with the cheap parameters.
+    fn derive<G: Group>(material: &G, password: &str, salt: &[u8]) -> G::Scalar {
+        derive_x_with_params(material, password.as_bytes(), salt, params())
+            .unwrap()
+            .expose()
+            .clone()
+    }
+
+    /// Runs the protocol for the secret `registered`, answering with the secret `login`.
+    fn login<G: Group>(
+        material: G,
+        registered: &G::Scalar,
+        login: &G::Scalar,
+    ) -> VerificationResult {
+        let statement = ChaumPedersenStatement::new(&material, registered);
+        let protocol = ChaumPedersen::new(material);
+        let mut rng = rand::thread_rng();
+        let (commitment, k) = protocol.commit(&statement, login, &mut rng);
+        let c = protocol.challenge(&mut rng);
+        let s = protocol.respond(login, k, &c);
+        protocol.verify(&statement, &commitment, &c, &s)
+    }
+
+    #[test]
+    fn test_derive_x() {
+        let material = Material::default();
+        let salt = generate_salt(&mut rand::thread_rng());
+        let x = derive(&material, "correct horse battery staple", &salt);
+        assert_eq!(x, derive(&material, "correct horse battery staple", &salt));
+        assert!(material.is_scalar(&x));
+        assert_ne!(x, derive(&material, "correct horse battery stapler", &salt));
+        assert_ne!(
+            x,
+            derive(&material, "correct horse battery staple", &[0; SALT_LEN])
+        );
+        assert_eq!(
+            login(material.clone(), &x, &x),
+            VerificationResult::ChallengeVerifiedSuccess
+        );
+        let wrong = derive(&material, "Tr0ub4dor&3", &salt);
+        assert_eq!(
+            login(material, &x, &wrong),
+            VerificationResult::ChallengeVerificationFailed
+        );
+
+        let material = RistrettoMaterial::default();
+        let x = derive(&material, "correct horse battery staple", &salt);
+        assert_eq!(
+            login(material, &x, &x),
+            VerificationResult::ChallengeVerifiedSuccess
+        );
+    }
+
+    /// Pins the secret derived from a password with the cheap parameters of the tests.
+    #[test]
+    fn test_known_answer() {
+        let material = known_material();
+        assert_eq!(
+            derive(&material, "password", b"saltsaltsaltsalt"),
+            BigInt::from(7355396328454860612u64)
+        );
+    }
+
+    /// Test that a salt shorter than `MIN_SALT_LEN` is refused with its length and the minimum.
+    #[test]
+    fn test_short_salt() {
+        let error = derive_x(&known_material(), b"password", b"short").unwrap_err();
+        assert_eq!(
+            error,
+            ProtocolError::TooShort {
+                name: "salt",
+                min: MIN_SALT_LEN,
+                actual: 5
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "the salt has 5 bytes, fewer than the minimum of 8"
+        );
+    }
+}
@@ -0,0 +1,234 @@
+//! This module contains the arbitrary-precision prime arithmetic used to build the protocol material.
+//!
+//! - `is_probable_prime`: Miller-Rabin primality test over `BigInt`.
+//! - `generate_safe_prime`: Generates a safe prime `p = 2q + 1` of an exact bit length, where `q` is also prime.
+//! - `jacobi`: Jacobi symbol, used to check membership in the subgroup of quadratic residues without exponentiating.
+//!
+//! Candidates for the safe prime search are sieved incrementally against a table of small primes before running
+//! any modular exponentiation, which is what makes generating 2048 or 3072-bit groups practical.
+use num_bigint::{BigInt, RandBigInt};
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+/// Upper bound for the small primes used to sieve candidates.
+const SIEVE_LIMIT: usize = 1 << 16;
+
+/// Number of Miller-Rabin rounds. The probability of accepting a composite is at most `4^-MILLER_RABIN_ROUNDS`.
+const MILLER_RABIN_ROUNDS: usize = 40;
+
+/// Maximum distance walked from a random starting point before drawing a new one.
+const SEARCH_WINDOW: u64 = 1 << 24;
+
+/// Returns all primes smaller than `limit` using the sieve of Eratosthenes.
+fn small_primes(limit: usize) -> Vec<u64> {
+    let mut composite = vec![false; limit];
+    let mut primes = Vec::new();
+    for i in 2..limit {
+        if !composite[i] {
+            primes.push(i as u64);
+            let mut j = i * i;
+            while j < limit {
+                composite[j] = true;
+                j += i;
+            }
+        }
+    }
+    primes
+}
+
+/// Checks whether `n` is a probable prime using trial division followed by `MILLER_RABIN_ROUNDS` rounds of
+/// Miller-Rabin with random bases.
+pub fn is_probable_prime(n: &BigInt) -> bool {
+    let two = BigInt::from(2);
+    if n < &two {
+        return false;
+    }
+    for r in small_primes(1000) {
+        let r = BigInt::from(r);
+        if n == &r {
+            return true;
+        }
+        if (n % &r).is_zero() {
+            return false;
+        }
+    }
+    miller_rabin(n, MILLER_RABIN_ROUNDS)
+}
+
+/// Fermat test to base 2, used as a cheap filter before running Miller-Rabin.
+fn fermat_base_two(n: &BigInt) -> bool {
+    BigInt::from(2).modpow(&(n - 1), n).is_one()
+}
+
+/// Miller-Rabin test for an odd `n > 3`.
+fn miller_rabin(n: &BigInt, rounds: usize) -> bool {
+    let one = BigInt::one();
+    let n_minus_one: BigInt = n - 1;
+    let s = n_minus_one
+        .trailing_zeros()
+        .expect("n - 1 is non-zero for n > 3");
+    let d = &n_minus_one >> s;
+    let mut rng = rand::thread_rng();
+    'witness: for _ in 0..rounds {
+        let a = rng.gen_bigint_range(&BigInt::from(2), &n_minus_one);
+        let mut x = a.modpow(&d, n);
+        if x == one || x == n_minus_one {
+            continue;
+        }
+        for _ in 1..s {
+            x = (&x * &x) % n;
+            if x == n_minus_one {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// Computes the Jacobi symbol `(a / n)` with the binary algorithm.
+///
+/// For a prime `n` it is the Legendre symbol: `1` if `a` is a non-zero quadratic residue modulo `n`, `-1` if it is a
+/// non-residue and `0` if `n` divides `a`.
+///
+/// # Arguments
+///
+/// * `a` - Any integer.
+/// * `n` - A positive odd integer.
+///
+/// # Panics
+///
+/// Panics if `n` is not positive and odd.
+pub fn jacobi(a: &BigInt, n: &BigInt) -> i8 {
+    assert!(n.is_positive() && n.bit(0), "n must be positive and odd");
+    let low_bits = |n: &BigInt| n.iter_u64_digits().next().unwrap_or(0);
+    let mut a = ((a % n) + n) % n;
+    let mut n = n.clone();
+    let mut t = 1;
+    while !a.is_zero() {
+        let zeros = a.trailing_zeros().expect("a is non-zero");
+        a >>= zeros;
+        if zeros % 2 == 1 && matches!(low_bits(&n) % 8, 3 | 5) {
+            t = -t;
+        }
+        std::mem::swap(&mut a, &mut n);
+        if low_bits(&a) % 4 == 3 && low_bits(&n) % 4 == 3 {
+            t = -t;
+        }
+        a %= &n;
+    }
+    if n.is_one() {
+        t
+    } else {
+        0
+    }
+}
+
+/// Generates a safe prime `p` of exactly `bits` bits, such that `q = (p - 1) / 2` is also prime.
+///
+/// # Arguments
+///
+/// * `bits` - The bit length of `p`. It must be at least 8.
+///
+/// # Panics
+///
+/// Panics if `bits` is smaller than 8.
+pub fn generate_safe_prime(bits: usize) -> BigInt {
+    assert!(bits >= 8, "Safe primes must have at least 8 bits");
+    let q_bits = (bits - 1) as u64;
+    let lower_bound = BigInt::one() << (q_bits - 1);
+    // Only sieve with primes smaller than any candidate, so that a zero residue always means composite.
+    let sieve: Vec<u64> = small_primes(SIEVE_LIMIT)
+        .into_iter()
+        .skip(1)
+        .take_while(|r| BigInt::from(*r) < lower_bound)
+        .collect();
+    let mut rng = rand::thread_rng();
+    loop {
+        let mut start: BigInt = rng.gen_biguint(q_bits).into();
+        start.set_bit(q_bits - 1, true);
+        start.set_bit(0, true);
+        let residues: Vec<u64> = sieve
+            .iter()
+            .map(|r| {
+                (&start % r)
+                    .to_u64()
+                    .expect("residue is smaller than the sieve prime")
+            })
+            .collect();
+        for delta in (0..SEARCH_WINDOW).step_by(2) {
+            // A candidate is discarded if either q or 2q + 1 is divisible by a small prime.
+            let sieved = sieve.iter().zip(&residues).any(|(r, residue)| {
+                let m = (residue + delta) % r;
+                m == 0 || m == (r - 1) / 2
+            });
+            if sieved {
+                continue;
+            }
+            let q = &start + delta;
+            if q.bits() != q_bits {
+                break;
+            }
+            let p: BigInt = (&q << 1) + 1;
+            if fermat_base_two(&q)
+                && fermat_base_two(&p)
+                && miller_rabin(&q, MILLER_RABIN_ROUNDS)
+                && miller_rabin(&p, MILLER_RABIN_ROUNDS)
+            {
+                return p;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_probable_prime() {
+        assert!(is_probable_prime(&BigInt::from(2)));
+        assert!(is_probable_prime(&BigInt::from(7703)));
+        assert!(is_probable_prime(&BigInt::from(
+            2_305_843_009_213_693_951u64
+        )));
+        assert!(!is_probable_prime(&BigInt::from(1)));
+        assert!(!is_probable_prime(&BigInt::from(7701)));
+        // Carmichael number
+        assert!(!is_probable_prime(&BigInt::from(561)));
+    }
+
+    #[test]
+    fn test_jacobi() {
+        // Against Euler's criterion modulo a prime.
+        let p = BigInt::from(7703);
+        let exponent = (&p - 1) / 2;
+        for a in 0..200 {
+            let a = BigInt::from(a);
+            let euler = a.modpow(&exponent, &p);
+            let expected = if euler.is_zero() {
+                0
+            } else if euler.is_one() {
+                1
+            } else {
+                -1
+            };
+            assert_eq!(jacobi(&a, &p), expected, "a = {}", a);
+        }
+        // Composite modulus: (2 / 15) = (2 / 3) * (2 / 5) = 1, although 2 is not a square modulo 15.
+        assert_eq!(jacobi(&BigInt::from(2), &BigInt::from(15)), 1);
+        assert_eq!(jacobi(&BigInt::from(7), &BigInt::from(15)), -1);
+        assert_eq!(jacobi(&BigInt::from(-1), &BigInt::from(7)), -1);
+        assert_eq!(jacobi(&BigInt::from(5), &BigInt::from(15)), 0);
+    }
+
+    #[test]
+    fn test_generate_safe_prime() {
+        for bits in [8, 16, 64, 128] {
+            let p = generate_safe_prime(bits);
+            let q = (&p - 1) / 2;
+            assert_eq!(p.bits(), bits as u64);
+            assert!(is_probable_prime(&p));
+            assert!(is_probable_prime(&q));
+        }
+    }
+}
@@ -0,0 +1,285 @@
+//! This module contains the Ristretto255 backend of the Chaum-Pedersen ZK protocol.
+//!
+//! `RistrettoMaterial` implements `Group` with `RistrettoPoint` elements and `Scalar` values, so the protocol steps in
+//! `cp` (`Register`, `Commitment`, `Challenge`, `ChallengeResponse`, `VerificationRequest` and `Verification`) can be
+//! used with it in the same way as with the modular arithmetic `Material`. Points and scalars are encoded in 32 bytes.
+use crate::protocol::cp::{ProtocolStep, DEFAULT_H_SEED};
+use crate::protocol::encoding;
+use crate::protocol::group::Group;
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::{Identity, VartimeMultiscalarMul};
+use rand::{CryptoRng, RngCore};
+use sha2::Sha512;
+use subtle::ConstantTimeEq;
+use typed_builder::TypedBuilder;
+
+/// Domain separation tag for the derivation of `H`.
+const H_DERIVATION_DST: &[u8] = b"zk-cp-protocol/ristretto255/derive-h/sha512/v1";
+
+/// Public parameters of the protocol over Ristretto255.
+#[derive(Debug, Clone, TypedBuilder)]
+pub struct RistrettoMaterial {
+    pub g: RistrettoPoint,
+    pub h: RistrettoPoint,
+    /// The public seed `h` was derived from. See `RistrettoMaterial::derive_h`.
+    #[builder(setter(into))]
+    pub h_seed: String,
+}
+
+impl RistrettoMaterial {
+    /// Builds the material with the Ristretto255 basepoint as `G` and `H` derived from the given public seed.
+    ///
+    /// # Arguments
+    ///
+    /// * `seed` - The public seed to derive `H` from.
+    pub fn new(seed: &str) -> Self {
+        RistrettoMaterial {
+            g: RISTRETTO_BASEPOINT_POINT,
+            h: RistrettoMaterial::derive_h(seed),
+            h_seed: seed.to_string(),
+        }
+    }
+
+    /// Derives `H` by hashing the public seed to a point with SHA-512, so that nobody knows `log_G(H)`.
+    pub fn derive_h(seed: &str) -> RistrettoPoint {
+        let mut input = H_DERIVATION_DST.to_vec();
+        input.extend_from_slice(seed.as_bytes());
+        RistrettoPoint::hash_from_bytes::<Sha512>(&input)
+    }
+
+    /// Checks that `h` is the one derived from the recorded public seed.
+    pub fn verify_h(&self) -> bool {
+        RistrettoMaterial::derive_h(&self.h_seed) == self.h
+    }
+}
+
+impl Default for RistrettoMaterial {
+    fn default() -> Self {
+        RistrettoMaterial::new(DEFAULT_H_SEED)
+    }
+}
+
+impl ProtocolStep for RistrettoMaterial {}
+
+impl Group for RistrettoMaterial {
+    type Element = RistrettoPoint;
+    type Scalar = Scalar;
+
+    fn g(&self) -> &RistrettoPoint {
+        &self.g
+    }
+
+    fn h(&self) -> &RistrettoPoint {
+        &self.h
+    }
+
+    fn identity(&self) -> RistrettoPoint {
+        RistrettoPoint::identity()
+    }
+
+    fn exp(&self, base: &RistrettoPoint, exponent: &Scalar) -> RistrettoPoint {
+        base * exponent
+    }
+
+    fn mul(&self, a: &RistrettoPoint, b: &RistrettoPoint) -> RistrettoPoint {
+        a + b
+    }
+
+    /// Variable-base scalar multiplication is constant time in `curve25519-dalek`.
+    fn exp_secret(&self, base: &RistrettoPoint, exponent: &Scalar) -> RistrettoPoint {
+        base * exponent
+    }
+
+    fn element_ct_eq(&self, a: &RistrettoPoint, b: &RistrettoPoint) -> bool {
+        a.ct_eq(b).into()
+    }
+
+    /// Every `RistrettoPoint` is an element of the prime-order group, so only the identity is rejected.
+    fn is_element(&self, e: &RistrettoPoint) -> bool {
+        e != &RistrettoPoint::identity()
+    }
+
+    /// Every `Scalar` is reduced: they can only be built through reducing constructors or `decode_scalar`, which
+    /// rejects non-canonical encodings.
+    fn is_scalar(&self, _s: &Scalar) -> bool {
+        true
+    }
+
+    fn random_scalar<R: RngCore + CryptoRng>(&self, rng: &mut R) -> Scalar {
+        loop {
+            let s = Scalar::random(rng);
+            if s != Scalar::ZERO {
+                return s;
+            }
+        }
+    }
+
+    fn scalar_add(&self, a: &Scalar, b: &Scalar) -> Scalar {
+        a + b
+    }
+
+    fn scalar_sub(&self, a: &Scalar, b: &Scalar) -> Scalar {
+        a - b
+    }
+
+    fn scalar_mul(&self, a: &Scalar, b: &Scalar) -> Scalar {
+        a * b
+    }
+
+    fn scalar_invert(&self, a: &Scalar) -> Option<Scalar> {
+        (a != &Scalar::ZERO).then(|| a.invert())
+    }
+
+    /// Scalar arithmetic is constant time in `curve25519-dalek`.
+    fn scalar_mul_sub_secret(&self, k: &Scalar, c: &Scalar, x: &Scalar) -> Scalar {
+        k - c * x
+    }
+
+    fn scalar_from_u128(&self, n: u128) -> Scalar {
+        Scalar::from(n)
+    }
+
+    /// Uses the variable-time Straus / Pippenger multiscalar multiplication, as the inputs are public.
+    fn multi_exp(&self, terms: &[(&RistrettoPoint, &Scalar)]) -> RistrettoPoint {
+        RistrettoPoint::vartime_multiscalar_mul(
+            terms.iter().map(|(_, scalar)| *scalar),
+            terms.iter().map(|(point, _)| *point),
+        )
+    }
+
+    fn encode_element(&self, e: &RistrettoPoint) -> Vec<u8> {
+        e.compress().to_bytes().to_vec()
+    }
+
+    fn decode_element(&self, bytes: &[u8]) -> Option<RistrettoPoint> {
+        CompressedRistretto::from_slice(bytes).ok()?.decompress()
+    }
+
+    fn encode_scalar(&self, s: &Scalar) -> Vec<u8> {
+        s.to_bytes().to_vec()
+    }
+
+    fn decode_scalar(&self, bytes: &[u8]) -> Option<Scalar> {
+        Option::from(Scalar::from_canonical_bytes(bytes.try_into().ok()?))
+    }
+
+    fn group_id(&self) -> u8 {
+        encoding::RISTRETTO255_GROUP_ID
+    }
+
+    fn encode_params(&self) -> Vec<u8> {
+        [self.g.compress().to_bytes(), self.h.compress().to_bytes()].concat()
+    }
+
+    fn hash_to_scalar(&self, input: &[u8]) -> Scalar {
+        Scalar::hash_from_bytes::<Sha512>(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::cp::{
+        ChallengeResponse, ProtocolState, ProtocolTransition, Register, Verification,
+        VerificationResult,
+    };
+    use crate::protocol::error::RejectionReason;
+    use crate::protocol::test_support::hex;
+
+    fn prove(x: &Scalar, verifier_x: &Scalar) -> VerificationResult {
+        let material = RistrettoMaterial::default();
+        let register = Register::new(material.clone(), verifier_x);
+        let commitment = ProtocolState::from(register.clone()).change().into_inner();
+        let challenge = ProtocolState::from(material).change().into_inner();
+        let response = ProtocolState::from(
+            ChallengeResponse::builder()
+                .challenge(challenge.clone())
+                .material(commitment.material)
+                .x(*x)
+                .k(commitment.k)
+                .build(),
+        )
+        .change()
+        .into_inner();
+        ProtocolState::from(
+            Verification::builder()
+                .material(register.material)
+                .ys(register.ys)
+                .rs(commitment.rs)
+                .c(challenge.c)
+                .s(response.s)
+                .build(),
+        )
+        .change()
+        .into_inner()
+    }
+
+    #[test]
+    fn test_challenge_transition_change() {
+        let x = Scalar::random(&mut rand::thread_rng());
+        assert_eq!(prove(&x, &x), VerificationResult::ChallengeVerifiedSuccess);
+    }
+
+    #[test]
+    fn test_challenge_transition_wrong_secret() {
+        let x = Scalar::random(&mut rand::thread_rng());
+        assert_eq!(
+            prove(&(x + Scalar::ONE), &x),
+            VerificationResult::ChallengeVerificationFailed
+        );
+    }
+
+    #[test]
+    fn test_verification_rejected() {
+        let material = RistrettoMaterial::default();
+        let point = material.g * Scalar::from(5u64);
+        let verification = Verification::builder()
+            .material(material)
+            .ys(vec![point, point])
+            .rs(vec![RistrettoPoint::identity(), point])
+            .c(Scalar::ONE)
+            .s(Scalar::ONE)
+            .build();
+        assert_eq!(
+            ProtocolState::from(verification).change().into_inner(),
+            VerificationResult::ChallengeRejected(RejectionReason::NotInGroup("r1"))
+        );
+    }
+
+    #[test]
+    fn test_encoding() {
+        let material = RistrettoMaterial::default();
+        assert!(material.verify_h());
+        assert_ne!(material.g, material.h);
+
+        let x = Scalar::random(&mut rand::thread_rng());
+        let register = Register::new(material.clone(), &x);
+        let bytes = material.encode_element(register.y1());
+        assert_eq!(bytes.len(), 32);
+        assert_eq!(material.decode_element(&bytes), Some(*register.y1()));
+        assert_eq!(material.decode_element(&[0xff; 32]), None);
+        let bytes = material.encode_scalar(&x);
+        assert_eq!(bytes.len(), 32);
+        assert_eq!(material.decode_scalar(&bytes), Some(x));
+        assert_eq!(material.decode_scalar(&[0xff; 32]), None);
+        assert_eq!(material.decode_scalar(&[0; 31]), None);
+    }
+
+    /// Pins the statement `(g^x, h^x)` of a fixed secret, so that any change to `h` or to the encoding of the points
+    /// is caught.
+    #[test]
+    fn test_known_answer() {
+        let material = RistrettoMaterial::default();
+        let register = Register::new(material.clone(), &Scalar::from(1_234_567_890u64));
+        assert_eq!(
+            hex(&material.encode_element(register.y1())),
+            "708d773ed01be5ac77abec04bd7fdfa8bf2523dc602423a392043d47511c145d"
+        );
+        assert_eq!(
+            hex(&material.encode_element(register.y2())),
+            "38d3443444a635dc58a235150fb9caa70fd1069b302412bfa4f4b90a3e3b0330"
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! This module contains the Schnorr proof of knowledge of a discrete logarithm, as a `SigmaProtocol`.
+//!
+//! The prover knows `x` such that `y = g^x`:
+//! 1. The prover commits to `r = g^k` for a random non-zero `k`.
+//! 2. The verifier sends a random challenge `c`.
+//! 3. The prover answers with `s = k - c * x`.
+//!
+//! The verifier accepts if `r = g^s * y^c`. It is the Chaum-Pedersen protocol with a single base.
+use crate::protocol::cp::{Material, VerificationResult};
+use crate::protocol::group::Group;
+use crate::protocol::secret::Secret;
+use crate::protocol::sigma::{self, SigmaProtocol};
+use crate::protocol::simulator;
+use rand::{CryptoRng, RngCore};
+
+/// Schnorr proof of knowledge of `x` such that `y = g^x`.
+#[derive(Debug, Clone)]
+pub struct Schnorr<G: Group = Material> {
+    pub material: G,
+}
+
+impl<G: Group> Schnorr<G> {
+    /// Creates the protocol over the given group.
+    pub fn new(material: G) -> Self {
+        Schnorr { material }
+    }
+}
+
+impl<G: Group> SigmaProtocol for Schnorr<G> {
+    type Group = G;
+    /// The public value `y = g^x`.
+    type Statement = G::Element;
+    /// The secret `x`.
+    type Witness = G::Scalar;
+    /// The commitment `r = g^k`.
+    type Commitment = G::Element;
+    /// The nonce `k`.
+    type Nonce = Secret<G::Scalar>;
+    /// The response `s = k - c * x`.
+    type Response = G::Scalar;
+
+    fn material(&self) -> &G {
+        &self.material
+    }
+
+    fn commit<R: RngCore + CryptoRng>(
+        &self,
+        _: &G::Element,
+        _: &G::Scalar,
+        rng: &mut R,
+    ) -> (G::Element, Secret<G::Scalar>) {
+        let k = self.material.random_scalar(rng);
+        (
+            self.material.exp_secret(self.material.g(), &k),
+            Secret::new(k),
+        )
+    }
+
+    fn respond(&self, x: &G::Scalar, k: Secret<G::Scalar>, c: &G::Scalar) -> G::Scalar {
+        self.material.scalar_mul_sub_secret(k.expose(), c, x)
+    }
+
+    fn verify(
+        &self,
+        y: &G::Element,
+        r: &G::Element,
+        c: &G::Scalar,
+        s: &G::Scalar,
+    ) -> VerificationResult {
+        let material = &self.material;
+        let valid = sigma::validate(material, &[("y", y), ("r", r)], &[("c", c), ("s", s)]);
+        if let Err(reason) = valid {
+            tracing::info!("Schnorr proof rejected: {}", reason);
+            return VerificationResult::ChallengeRejected(reason);
+        }
+        let r_prime = material.multi_exp(&[(material.g(), s), (y, c)]);
+        if material.element_ct_eq(r, &r_prime) {
+            VerificationResult::ChallengeVerifiedSuccess
+        } else {
+            VerificationResult::ChallengeVerificationFailed
+        }
+    }
+
+    /// Draws `s` uniformly and solves `r = g^s * y^c`, redrawing `s` whenever `r` would be the identity.
+    fn simulate<R: RngCore + CryptoRng>(
+        &self,
+        y: &G::Element,
+        c: &G::Scalar,
+        rng: &mut R,
+    ) -> (G::Element, G::Scalar) {
+        let material = &self.material;
+        let identity = material.identity();
+        loop {
+            let s = simulator::uniform_scalar(material, rng);
+            let r = material.multi_exp(&[(material.g(), &s), (y, c)]);
+            if r != identity {
+                return (r, s);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::error::RejectionReason;
+    use num_bigint::BigInt;
+
+    #[test]
+    fn test_verify_rejected() {
+        let material = Material::generate(64, None).unwrap();
+        let schnorr = Schnorr::new(material.clone());
+        let y = material.exp(&material.g, &BigInt::from(5));
+        let one = BigInt::from(1);
+        assert_eq!(
+            schnorr.verify(&y, &one, &one, &one),
+            VerificationResult::ChallengeRejected(RejectionReason::NotInGroup("r"))
+        );
+        assert_eq!(
+            schnorr.verify(&y, &y, &material.q, &one),
+            VerificationResult::ChallengeRejected(RejectionReason::OutOfRange("c"))
+        );
+    }
+}
@@ -0,0 +1,99 @@
+//! This module contains the `Secret` wrapper holding the secret values of the prover, `x` and `k`.
+//!
+//! A `Secret`:
+//! - Is wiped from memory when dropped, see below for the limits of wiping a `BigInt`.
+//! - Prints as `Secret([REDACTED])` in `Debug`, so it never ends up in logs.
+//! - Does not implement `Clone`, so copies of the secret are never made by accident. The value can only be read
+//!   through `Secret::expose`.
+//!
+//! `Scalar`s are wiped with the `zeroize` crate, whose volatile writes the compiler cannot remove. Wiping a `BigInt`
+//! is only best-effort: `num-bigint` gives no access to its digit buffer, so the zeros are written through its public
+//! API with ordinary stores, which the compiler may still drop since the buffer is never read again. Copies of the
+//! secrets made by the arithmetic are not wiped either: the reduced copies of `Material::reduce`, and the `BigInt`
+//! operands and results of `ct` and the buffers `num-bigint` allocates for them. `ct` zeroizes its own fixed-width
+//! operands and byte buffers. A `Secret<BigInt>` keeps `x` and `k` out of logs and shortens their lifetime in memory,
+//! but does not guarantee that no copy survives.
+use curve25519_dalek::scalar::Scalar;
+use num_bigint::{BigInt, Sign};
+use std::fmt::{Debug, Formatter};
+use std::sync::atomic::{compiler_fence, Ordering};
+
+/// Values which can be overwritten with zeros in place.
+pub trait Zeroizable {
+    /// Overwrites the value with zeros.
+    fn zeroize(&mut self);
+}
+
+impl Zeroizable for BigInt {
+    /// Overwrites every digit of the integer with zero, on a best-effort basis as described in the module
+    /// documentation. The digits are written into the existing buffer, which is large enough for them, so it is not
+    /// reallocated, but the writes are not volatile and the buffer is truncated afterwards.
+    fn zeroize(&mut self) {
+        let digits = self.iter_u32_digits().len();
+        self.assign_from_slice(Sign::Plus, &vec![0; digits]);
+        compiler_fence(Ordering::SeqCst);
+    }
+}
+
+impl Zeroizable for Scalar {
+    fn zeroize(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+/// Secret value, wiped on drop and redacted in `Debug`.
+pub struct Secret<T: Zeroizable>(T);
+
+impl<T: Zeroizable> Secret<T> {
+    /// Wraps a secret value.
+    pub fn new(value: T) -> Self {
+        Secret(value)
+    }
+
+    /// Returns a reference to the secret value.
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Zeroizable> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Secret::new(value)
+    }
+}
+
+impl<T: Zeroizable> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl<T: Zeroizable> Debug for Secret<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Secret([REDACTED])")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_traits::Zero;
+
+    #[test]
+    fn test_zeroize() {
+        let mut n = BigInt::parse_bytes(b"123456789012345678901234567890123456789", 10).unwrap();
+        n.zeroize();
+        assert!(n.is_zero());
+
+        let mut s = Scalar::from(42u64);
+        Zeroizable::zeroize(&mut s);
+        assert_eq!(s, Scalar::ZERO);
+    }
+
+    #[test]
+    fn test_debug_redacted() {
+        let secret = Secret::new(BigInt::from(424242));
+        assert_eq!(format!("{:?}", secret), "Secret([REDACTED])");
+        assert_eq!(secret.expose(), &BigInt::from(424242));
+    }
+}
@@ -0,0 +1,196 @@
+//! This module contains the `SigmaProtocol` abstraction of three-move proofs of knowledge.
+//!
+//! A sigma protocol proves knowledge of a secret `Witness` for a public `Statement` in three moves:
+//! 1. `commit`: The prover sends a `Commitment`, keeping a secret `Nonce`.
+//! 2. `challenge`: The verifier answers with a random challenge scalar.
+//! 3. `respond`: The prover sends a `Response`, computed from the witness, the nonce and the challenge.
+//!
+//! The verifier then accepts or rejects with `verify`. Every sigma protocol also has a `simulate` function producing
+//! accepting transcripts for a given challenge without the witness, which makes it honest-verifier zero-knowledge and
+//! is what proofs composed of several sigma protocols are built from.
+//!
+//! The crate provides three implementations:
+//! - `ChaumPedersen`: Knowledge of `x` such that `y1 = g^x` and `y2 = h^x`.
+//! - `Dleq`: Knowledge of `x` such that `y_i = b_i^x` for any list of bases `b_i`. The typestate steps in `cp` run
+//!   this protocol, with the bases `g` and `h` by default.
+//! - `Schnorr`: Knowledge of `x` such that `y = g^x`.
+use crate::protocol::cp::VerificationResult;
+use crate::protocol::error::RejectionReason;
+use crate::protocol::group::Group;
+use rand::{CryptoRng, RngCore};
+use std::fmt::Debug;
+
+/// Scalar of the group a sigma protocol runs on, the type of its challenges.
+pub type SigmaScalar<S> = <<S as SigmaProtocol>::Group as Group>::Scalar;
+
+/// Three-move proof of knowledge of a witness for a statement.
+pub trait SigmaProtocol {
+    /// The group the protocol runs on.
+    type Group: Group;
+    /// Public statement the prover proves a witness for.
+    type Statement: Clone + Debug;
+    /// Secret witness of the prover.
+    type Witness;
+    /// First message of the prover.
+    type Commitment: Clone + Debug;
+    /// Secret state of the prover between `commit` and `respond`.
+    type Nonce;
+    /// Last message of the prover.
+    type Response: Clone + Debug;
+
+    /// Returns the group the protocol runs on.
+    fn material(&self) -> &Self::Group;
+
+    /// Computes the commitment of the prover.
+    ///
+    /// # Arguments
+    ///
+    /// * `statement` - The public statement.
+    /// * `witness` - The witness of the prover.
+    /// * `rng` - The RNG to draw the nonce from.
+    ///
+    /// # Returns
+    ///
+    /// The commitment to send to the verifier, and the nonce to keep until `respond`.
+    fn commit<R: RngCore + CryptoRng>(
+        &self,
+        statement: &Self::Statement,
+        witness: &Self::Witness,
+        rng: &mut R,
+    ) -> (Self::Commitment, Self::Nonce);
+
+    /// Draws the challenge of the verifier.
+    fn challenge<R: RngCore + CryptoRng>(&self, rng: &mut R) -> SigmaScalar<Self> {
+        self.material().random_scalar(rng)
+    }
+
+    /// Computes the response of the prover to the challenge.
+    ///
+    /// # Arguments
+    ///
+    /// * `witness` - The witness of the prover.
+    /// * `nonce` - The nonce returned by `commit`, which is consumed.
+    /// * `challenge` - The challenge of the verifier.
+    fn respond(
+        &self,
+        witness: &Self::Witness,
+        nonce: Self::Nonce,
+        challenge: &SigmaScalar<Self>,
+    ) -> Self::Response;
+
+    /// Verifies a transcript, validating every received value first.
+    ///
+    /// # Returns
+    ///
+    /// `ChallengeRejected` if any of the values is malformed, otherwise whether the transcript is accepting.
+    fn verify(
+        &self,
+        statement: &Self::Statement,
+        commitment: &Self::Commitment,
+        challenge: &SigmaScalar<Self>,
+        response: &Self::Response,
+    ) -> VerificationResult;
+
+    /// Produces an accepting transcript for the given challenge without knowing the witness.
+    ///
+    /// # Arguments
+    ///
+    /// * `statement` - The public statement.
+    /// * `challenge` - The challenge the transcript must have.
+    /// * `rng` - The RNG to draw the response from.
+    ///
+    /// # Returns
+    ///
+    /// The commitment and the response of the transcript.
+    fn simulate<R: RngCore + CryptoRng>(
+        &self,
+        statement: &Self::Statement,
+        challenge: &SigmaScalar<Self>,
+        rng: &mut R,
+    ) -> (Self::Commitment, Self::Response);
+}
+
+/// Checks that the received elements are non-trivial elements of the group and the received scalars are reduced.
+///
+/// # Returns
+///
+/// A `Result` which is `Ok(())` if all values are valid, or the `RejectionReason` of the first invalid value.
+pub(crate) fn validate<G: Group>(
+    material: &G,
+    elements: &[(&'static str, &G::Element)],
+    scalars: &[(&'static str, &G::Scalar)],
+) -> Result<(), RejectionReason> {
+    for (name, e) in elements {
+        if !material.is_element(e) {
+            return Err(RejectionReason::NotInGroup(name));
+        }
+    }
+    for (name, s) in scalars {
+        if !material.is_scalar(s) {
+            return Err(RejectionReason::OutOfRange(name));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::cp::{ChaumPedersen, ChaumPedersenStatement, Material};
+    use crate::protocol::ristretto::RistrettoMaterial;
+    use crate::protocol::schnorr::Schnorr;
+    use crate::protocol::test_support::run;
+    use num_bigint::BigInt;
+
+    /// Checks that an honest run and a simulated transcript are accepted, and a run with the wrong witness is not.
+    fn check<S: SigmaProtocol>(
+        protocol: S,
+        statement: S::Statement,
+        witness: S::Witness,
+        wrong: S::Witness,
+    ) {
+        assert_eq!(
+            run(&protocol, &statement, &witness),
+            VerificationResult::ChallengeVerifiedSuccess
+        );
+        assert_eq!(
+            run(&protocol, &statement, &wrong),
+            VerificationResult::ChallengeVerificationFailed
+        );
+        let mut rng = rand::thread_rng();
+        let c = protocol.challenge(&mut rng);
+        let (commitment, response) = protocol.simulate(&statement, &c, &mut rng);
+        assert_eq!(
+            protocol.verify(&statement, &commitment, &c, &response),
+            VerificationResult::ChallengeVerifiedSuccess
+        );
+    }
+
+    #[test]
+    fn test_chaum_pedersen() {
+        let material = Material::generate(256, None).unwrap();
+        let x = BigInt::from(42);
+        let statement = ChaumPedersenStatement::new(&material, &x);
+        check(ChaumPedersen::new(material), statement, x, BigInt::from(43));
+
+        let material = RistrettoMaterial::default();
+        let x = material.random_scalar(&mut rand::thread_rng());
+        let statement = ChaumPedersenStatement::new(&material, &x);
+        let wrong = material.scalar_from_u128(1);
+        check(ChaumPedersen::new(material), statement, x, wrong);
+    }
+
+    #[test]
+    fn test_schnorr() {
+        let material = Material::generate(256, None).unwrap();
+        let x = BigInt::from(42);
+        let y = material.exp(&material.g, &x);
+        check(Schnorr::new(material), y, x, BigInt::from(43));
+
+        let material = RistrettoMaterial::default();
+        let x = material.random_scalar(&mut rand::thread_rng());
+        let y = material.exp(material.g(), &x);
+        let wrong = material.scalar_from_u128(1);
+        check(Schnorr::new(material), y, x, wrong);
+    }
+}
@@ -0,0 +1,214 @@
+//! This module contains the honest-verifier zero-knowledge simulator of the Chaum-Pedersen ZK protocol.
+//!
+//! Given only the public values `y1` and `y2`, the simulator produces transcripts `(r1, r2, c, s)` that pass
+//! verification and follow the same distribution as the transcripts of a real run with an honest verifier. It works
+//! backwards: it picks the challenge `c` and the response `s` first, and then solves the verification equations for
+//! the commitments:
+//!
+//! `r1 = g^s * y1^c` and `r2 = h^s * y2^c`
+//!
+//! In a real run `k` is a uniformly random non-zero scalar and `c` is drawn by `Group::random_scalar`, so `(c, s)` is
+//! uniform among the pairs for which `r1` is not the identity. The simulator draws `c` the same way and `s`
+//! uniformly, and redraws `s` whenever `r1` would be the identity, which gives the same distribution without knowing
+//! `x`. Since anyone can produce such transcripts, a transcript reveals nothing about `x`.
+//!
+//! The simulator is also the building block of OR-proofs, where the branches the prover does not know the secret of
+//! are simulated for a challenge fixed in advance, see `simulate_with_challenge`.
+use crate::protocol::cp::Verification;
+use crate::protocol::group::Group;
+use rand::{CryptoRng, RngCore};
+
+/// Simulates a transcript for `y1` and `y2` without knowing their discrete logarithm.
+///
+/// # Arguments
+///
+/// * `material` - The group the protocol runs on.
+/// * `y1`, `y2` - The public values `g^x` and `h^x`.
+///
+/// # Returns
+///
+/// A `Verification` holding the simulated transcript, which passes verification.
+pub fn simulate<G: Group>(material: &G, y1: &G::Element, y2: &G::Element) -> Verification<G> {
+    simulate_with_rng(material, y1, y2, &mut rand::thread_rng())
+}
+
+/// Same as `simulate`, drawing the randomness from the given RNG.
+pub fn simulate_with_rng<G: Group, R: RngCore + CryptoRng>(
+    material: &G,
+    y1: &G::Element,
+    y2: &G::Element,
+    rng: &mut R,
+) -> Verification<G> {
+    let c = material.random_scalar(rng);
+    simulate_with_challenge(material, y1, y2, c, rng)
+}
+
+/// Simulates a transcript for `y1` and `y2` with the given challenge.
+///
+/// # Arguments
+///
+/// * `material` - The group the protocol runs on.
+/// * `y1`, `y2` - The public values `g^x` and `h^x`.
+/// * `c` - The challenge the transcript must have.
+/// * `rng` - The RNG to draw the response from.
+pub fn simulate_with_challenge<G: Group, R: RngCore + CryptoRng>(
+    material: &G,
+    y1: &G::Element,
+    y2: &G::Element,
+    c: G::Scalar,
+    rng: &mut R,
+) -> Verification<G> {
+    let identity = material.identity();
+    loop {
+        let s = uniform_scalar(material, rng);
+        let r1 = material.multi_exp(&[(material.g(), &s), (y1, &c)]);
+        if r1 == identity {
+            continue;
+        }
+        let r2 = material.multi_exp(&[(material.h(), &s), (y2, &c)]);
+        return Verification::builder()
+            .material(material.clone())
+            .ys(vec![y1.clone(), y2.clone()])
+            .rs(vec![r1, r2])
+            .c(c)
+            .s(s)
+            .build();
+    }
+}
+
+/// Draws a scalar statistically close to uniform, zero included, which `Group::random_scalar` never returns.
+pub(crate) fn uniform_scalar<G: Group, R: RngCore + CryptoRng>(
+    material: &G,
+    rng: &mut R,
+) -> G::Scalar {
+    let mut bytes = [0u8; 64];
+    rng.fill_bytes(&mut bytes);
+    material.hash_to_scalar(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::cp::{
+        ChallengeResponse, Material, ProtocolState, ProtocolTransition, Register,
+        VerificationResult,
+    };
+    use crate::protocol::ristretto::RistrettoMaterial;
+    use num_bigint::BigInt;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+    use std::collections::HashMap;
+
+    /// Group of order 11 modulo 23, small enough to observe the whole distribution of the transcripts.
+    fn small_material() -> Material {
+        Material::builder().p(23).q(11).g(4).h(9).build().unwrap()
+    }
+
+    /// Runs the real protocol, with an honest verifier, and returns the transcript.
+    fn real<R: RngCore + CryptoRng>(material: &Material, x: &BigInt, rng: &mut R) -> Verification {
+        let register = Register::new(material.clone(), x);
+        let commitment = ProtocolState::from(register.clone())
+            .change_with_rng(rng)
+            .into_inner();
+        let challenge = ProtocolState::from(material.clone())
+            .change_with_rng(rng)
+            .into_inner();
+        let c = challenge.c.clone();
+        let response = ProtocolState::from(
+            ChallengeResponse::builder()
+                .challenge(challenge)
+                .material(material.clone())
+                .x(x.clone())
+                .k(commitment.k)
+                .build(),
+        )
+        .change()
+        .into_inner();
+        Verification::builder()
+            .material(material.clone())
+            .ys(register.ys)
+            .rs(commitment.rs)
+            .c(c)
+            .s(response.s)
+            .build()
+    }
+
+    /// Counts how many times every `(r1, r2, c, s)` appears.
+    fn histogram(transcripts: impl Iterator<Item = Verification>) -> HashMap<Vec<BigInt>, u32> {
+        let mut histogram = HashMap::new();
+        for v in transcripts {
+            let [r1, r2]: [BigInt; 2] = v.rs.try_into().unwrap();
+            *histogram.entry(vec![r1, r2, v.c, v.s]).or_default() += 1;
+        }
+        histogram
+    }
+
+    #[test]
+    fn test_simulated_transcripts_verify() {
+        let material = Material::generate(256, None).unwrap();
+        let register = Register::new(material.clone(), &BigInt::from(42));
+        for _ in 0..8 {
+            let simulated = simulate(&material, register.y1(), register.y2());
+            assert_eq!(
+                ProtocolState::from(simulated).change().into_inner(),
+                VerificationResult::ChallengeVerifiedSuccess
+            );
+        }
+
+        let material = RistrettoMaterial::default();
+        let x = material.random_scalar(&mut rand::thread_rng());
+        let register = Register::new(material.clone(), &x);
+        let c = material.scalar_from_u128(7);
+        let simulated = simulate_with_challenge(
+            &material,
+            register.y1(),
+            register.y2(),
+            c,
+            &mut rand::thread_rng(),
+        );
+        assert_eq!(simulated.c, c);
+        assert_eq!(
+            ProtocolState::from(simulated).change().into_inner(),
+            VerificationResult::ChallengeVerifiedSuccess
+        );
+    }
+
+    /// Compares the distributions of real and simulated transcripts with a two-sample chi-squared test.
+    ///
+    /// With `q = 11` there are `10 * 10` possible transcripts, so the statistic follows a chi-squared distribution with
+    /// 99 degrees of freedom when both samples come from the same distribution. Its mean is 99 and its standard
+    /// deviation 14; the threshold of 170 is five standard deviations away.
+    #[test]
+    fn test_simulated_distribution() {
+        const SAMPLES: usize = 20_000;
+        let material = small_material();
+        assert_eq!(material.validate(), Ok(()));
+        let x = BigInt::from(7);
+        let register = Register::new(material.clone(), &x);
+
+        let mut rng = ChaCha20Rng::seed_from_u64(1);
+        let real = histogram((0..SAMPLES).map(|_| real(&material, &x, &mut rng)));
+        let mut rng = ChaCha20Rng::seed_from_u64(2);
+        let simulated = histogram(
+            (0..SAMPLES)
+                .map(|_| simulate_with_rng(&material, register.y1(), register.y2(), &mut rng)),
+        );
+
+        assert_eq!(real.len(), 100);
+        let mut support: Vec<_> = real.keys().collect();
+        support.sort();
+        let mut simulated_support: Vec<_> = simulated.keys().collect();
+        simulated_support.sort();
+        assert_eq!(support, simulated_support);
+
+        let statistic: f64 = support
+            .iter()
+            .map(|transcript| {
+                let a = f64::from(real[*transcript]);
+                let b = f64::from(simulated[*transcript]);
+                (a - b).powi(2) / (a + b)
+            })
+            .sum();
+        assert!(statistic < 170.0, "chi-squared statistic {}", statistic);
+    }
+}
@@ -0,0 +1,31 @@
+//! This module contains the fixtures and helpers shared by the tests of the protocol: the 64-bit material of the
+//! known-answer tests, the hexadecimal encoding of their expected values, and a full run of any `SigmaProtocol`.
+use crate::protocol::cp::{Material, VerificationResult};
+use crate::protocol::sigma::SigmaProtocol;
+use num_bigint::BigInt;
+
+pub(crate) use crate::protocol::cp::to_hex as hex;
+
+/// Material of the known-answer tests, with 64-bit parameters.
+pub(crate) fn known_material() -> Material {
+    Material::builder()
+        .p(BigInt::from(18446744073709550147u64))
+        .q(BigInt::from(9223372036854775073u64))
+        .g(4)
+        .h(9)
+        .build()
+        .unwrap()
+}
+
+/// Runs the three moves of the protocol and verifies the resulting transcript.
+pub(crate) fn run<S: SigmaProtocol>(
+    protocol: &S,
+    statement: &S::Statement,
+    witness: &S::Witness,
+) -> VerificationResult {
+    let mut rng = rand::thread_rng();
+    let (commitment, nonce) = protocol.commit(statement, witness, &mut rng);
+    let c = protocol.challenge(&mut rng);
+    let response = protocol.respond(witness, nonce, &c);
+    protocol.verify(statement, &commitment, &c, &response)
+}
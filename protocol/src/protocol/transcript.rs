@@ -0,0 +1,161 @@
+//! This module contains the `Transcript` the Fiat-Shamir challenges are derived from.
+//!
+//! The design follows [Merlin](https://merlin.cool): every message absorbed into the transcript is labelled, and both
+//! the label and the message are prefixed with their length, so two different sequences of messages can never produce
+//! the same transcript. The state is a running SHA-512 hash. Deriving a challenge absorbs the challenge label, hashes
+//! the current state, and feeds the result back into the transcript, so later challenges depend on the earlier ones.
+//!
+//! Before deriving a challenge, the protocol absorbs everything the proof must be bound to:
+//! - The protocol label, given to `Transcript::new`, which separates the proofs of different applications.
+//! - The `Material` (or any other `Group`) the proof runs on.
+//! - The user and the `AuthId` of the session, or a value unique to the session when the prover commits before the
+//!   verifier has issued an `AuthId`.
+//! - Arbitrary application context, such as the identity of the verifier or the purpose of the proof.
+//!
+//! A proof produced with a transcript only verifies against a transcript with exactly the same messages, which stops
+//! proofs being relayed between services, users or sessions.
+use crate::protocol::cp::AuthId;
+use crate::protocol::group::Group;
+use sha2::{Digest, Sha512};
+
+/// Domain separation tag of every transcript.
+const TRANSCRIPT_DST: &[u8] = b"zk-cp-protocol/transcript/v1";
+
+/// Labelled, length-prefixed transcript of the public messages of a proof.
+#[derive(Debug, Clone)]
+pub struct Transcript {
+    hasher: Sha512,
+}
+
+impl Transcript {
+    /// Creates a transcript for the given protocol label.
+    ///
+    /// # Arguments
+    ///
+    /// * `label` - The label of the application protocol, e.g. `b"my-service/login"`.
+    pub fn new(label: &[u8]) -> Self {
+        let mut transcript = Transcript {
+            hasher: Sha512::new(),
+        };
+        transcript.append_message(b"dom-sep", TRANSCRIPT_DST);
+        transcript.append_message(b"protocol", label);
+        transcript
+    }
+
+    /// Absorbs a labelled message.
+    ///
+    /// # Arguments
+    ///
+    /// * `label` - The label of the message.
+    /// * `message` - The message.
+    pub fn append_message(&mut self, label: &[u8], message: &[u8]) {
+        self.hasher.update((label.len() as u64).to_be_bytes());
+        self.hasher.update(label);
+        self.hasher.update((message.len() as u64).to_be_bytes());
+        self.hasher.update(message);
+    }
+
+    /// Absorbs the public parameters of the group.
+    pub fn append_material<G: Group>(&mut self, material: &G) {
+        self.append_message(b"material", &material.encode_params());
+    }
+
+    /// Absorbs a group element.
+    pub fn append_element<G: Group>(&mut self, label: &[u8], material: &G, e: &G::Element) {
+        self.append_message(label, &material.encode_element(e));
+    }
+
+    /// Absorbs the user the proof is for.
+    pub fn append_user(&mut self, user: &str) {
+        self.append_message(b"user", user.as_bytes());
+    }
+
+    /// Absorbs the authentication session the proof is for.
+    pub fn append_auth_id(&mut self, auth_id: &AuthId) {
+        self.append_message(b"auth-id", auth_id.as_bytes());
+    }
+
+    /// Absorbs a value unique to the session, such as a counter or the current time.
+    ///
+    /// The interactive prover derives its nonce before the verifier issues an `AuthId`, so this value is what keeps the
+    /// nonces of two logins apart when the RNG is broken. See the `nonce` module.
+    pub fn append_session(&mut self, session: &[u8]) {
+        self.append_message(b"session", session);
+    }
+
+    /// Absorbs arbitrary application context, such as the identity of the verifier.
+    pub fn append_context(&mut self, context: &[u8]) {
+        self.append_message(b"context", context);
+    }
+
+    /// Returns a digest of everything absorbed so far, without changing the transcript.
+    ///
+    /// The digest is used to bind the nonce of the prover to the context of the proof, and is never sent anywhere.
+    pub fn digest(&self) -> Vec<u8> {
+        self.hasher.clone().finalize().to_vec()
+    }
+
+    /// Derives a challenge scalar from everything absorbed so far.
+    ///
+    /// The derived bytes are absorbed back into the transcript, so that a second challenge is different from the
+    /// first one.
+    ///
+    /// # Arguments
+    ///
+    /// * `label` - The label of the challenge.
+    /// * `material` - The group the challenge is a scalar of.
+    pub fn challenge_scalar<G: Group>(&mut self, label: &[u8], material: &G) -> G::Scalar {
+        self.append_message(b"challenge", label);
+        let digest = self.hasher.clone().finalize();
+        self.append_message(b"challenge-output", &digest);
+        material.hash_to_scalar(&digest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::cp::Material;
+
+    #[test]
+    fn test_challenge_scalar() {
+        let material = Material::default();
+        let mut transcript = Transcript::new(b"test");
+        transcript.append_user("alice");
+        let c1 = transcript.clone().challenge_scalar(b"c", &material);
+        assert_eq!(c1, transcript.clone().challenge_scalar(b"c", &material));
+        assert!(material.is_scalar(&c1));
+
+        let c2 = transcript.challenge_scalar(b"c", &material);
+        assert_eq!(c1, c2);
+        let c3 = transcript.challenge_scalar(b"c", &material);
+        assert_ne!(c2, c3);
+    }
+
+    #[test]
+    fn test_domain_separation() {
+        let material = Material::default();
+        let challenge = |mut transcript: Transcript| transcript.challenge_scalar(b"c", &material);
+
+        let mut alice = Transcript::new(b"test");
+        alice.append_user("alice");
+        let mut bob = Transcript::new(b"test");
+        bob.append_user("bob");
+        assert_ne!(challenge(alice.clone()), challenge(bob));
+
+        let mut other_protocol = Transcript::new(b"other");
+        other_protocol.append_user("alice");
+        assert_ne!(challenge(alice.clone()), challenge(other_protocol));
+
+        // Moving bytes between the label and the message must change the transcript.
+        let mut a = Transcript::new(b"test");
+        a.append_message(b"ab", b"c");
+        let mut b = Transcript::new(b"test");
+        b.append_message(b"a", b"bc");
+        assert_ne!(challenge(a), challenge(b));
+
+        let mut session = alice.clone();
+        session.append_auth_id(&AuthId::from("session"));
+        assert_ne!(challenge(alice), challenge(session));
+    }
+}
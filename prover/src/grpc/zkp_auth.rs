@@ -4,10 +4,12 @@
 pub struct RegisterRequest {
     #[prost(string, tag = "1")]
     pub user: ::prost::alloc::string::String,
-    #[prost(int64, tag = "2")]
-    pub y1: i64,
-    #[prost(int64, tag = "3")]
-    pub y2: i64,
+    #[prost(bytes = "vec", tag = "2")]
+    pub y1: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub y2: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "4")]
+    pub material_fingerprint: ::prost::alloc::vec::Vec<u8>,
 }
 impl ::prost::Name for RegisterRequest {
     const NAME: &'static str = "RegisterRequest";
@@ -37,10 +39,12 @@ impl ::prost::Name for RegisterResponse {
 pub struct AuthenticationChallengeRequest {
     #[prost(string, tag = "1")]
     pub user: ::prost::alloc::string::String,
-    #[prost(int64, tag = "2")]
-    pub r1: i64,
-    #[prost(int64, tag = "3")]
-    pub r2: i64,
+    #[prost(bytes = "vec", tag = "2")]
+    pub r1: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub r2: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "4")]
+    pub material_fingerprint: ::prost::alloc::vec::Vec<u8>,
 }
 impl ::prost::Name for AuthenticationChallengeRequest {
     const NAME: &'static str = "AuthenticationChallengeRequest";
@@ -57,8 +61,8 @@ impl ::prost::Name for AuthenticationChallengeRequest {
 pub struct AuthenticationChallengeResponse {
     #[prost(string, tag = "1")]
     pub auth_id: ::prost::alloc::string::String,
-    #[prost(int32, tag = "2")]
-    pub c: i32,
+    #[prost(bytes = "vec", tag = "2")]
+    pub c: ::prost::alloc::vec::Vec<u8>,
 }
 impl ::prost::Name for AuthenticationChallengeResponse {
     const NAME: &'static str = "AuthenticationChallengeResponse";
@@ -75,8 +79,8 @@ impl ::prost::Name for AuthenticationChallengeResponse {
 pub struct AuthenticationAnswerRequest {
     #[prost(string, tag = "1")]
     pub auth_id: ::prost::alloc::string::String,
-    #[prost(int32, tag = "2")]
-    pub s: i32,
+    #[prost(bytes = "vec", tag = "2")]
+    pub s: ::prost::alloc::vec::Vec<u8>,
 }
 impl ::prost::Name for AuthenticationAnswerRequest {
     const NAME: &'static str = "AuthenticationAnswerRequest";
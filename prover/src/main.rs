@@ -1,11 +1,17 @@
 use clap::Parser;
-use num_bigint::BigInt;
-use num_traits::ToPrimitive;
+use num_bigint::{BigInt, Sign};
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::time::Duration;
 use tonic::transport::Endpoint;
+use zeroize::Zeroize;
 use zk_cp_protocol::protocol::cp::{
     Challenge, ChallengeResponse, MaterialSerde, ProtocolState, ProtocolTransition, Register,
 };
+use zk_cp_protocol::protocol::derivation;
+use zk_cp_protocol::protocol::group::Group;
+use zk_cp_protocol::protocol::password;
+use zk_cp_protocol::protocol::secret::Secret;
+use zk_cp_protocol::protocol::transcript::Transcript;
 use zk_prover::grpc::zkp_auth::{self, AuthenticationAnswerRequest};
 
 fn init_tracing() {
@@ -17,6 +23,11 @@ fn init_tracing() {
         .init();
 }
 
+/// Encodes an integer as unsigned big-endian bytes for sending through gRPC.
+fn to_bytes(n: &BigInt) -> Vec<u8> {
+    n.magnitude().to_bytes_be()
+}
+
 #[derive(Debug, Parser)]
 pub struct Verifier {
     #[clap(short, long, default_value = "http://localhost:50000")]
@@ -25,8 +36,21 @@ pub struct Verifier {
     #[clap(short, long, default_value = "user")]
     user: String,
 
-    #[clap(short, long)]
-    x: String,
+    /// The secret x, as a decimal integer.
+    #[clap(short, long, required_unless_present_any = ["password", "domain"])]
+    x: Option<String>,
+
+    /// Prompt for a password and derive the secret x from it and the salt of the material file.
+    #[clap(long, conflicts_with = "x")]
+    password: bool,
+
+    /// Derive the secret x of this verifier from the master secret and the label.
+    #[clap(long, requires = "master_secret_path", conflicts_with_all = ["x", "password"])]
+    domain: Option<String>,
+
+    /// File holding the master secret used with --domain: at least 16 random bytes, e.g. from /dev/urandom.
+    #[clap(long, requires = "domain")]
+    master_secret_path: Option<String>,
 
     #[clap(short, long, default_value = "../protocol/data/client_material.json")]
     material_path: String,
@@ -34,7 +58,7 @@ pub struct Verifier {
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let conf = Verifier::parse();
+    let mut conf = Verifier::parse();
     init_tracing();
     let endpoint = Endpoint::new(conf.prover_address.clone())?.timeout(Duration::from_secs(60));
     tracing::info!("Connecting to prover at {}", conf.prover_address);
@@ -43,42 +67,84 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut service = zkp_auth::auth_client::AuthClient::new(client);
 
     tracing::info!("Getting material from file {}", conf.material_path);
-    let material: MaterialSerde =
+    let material_serde: MaterialSerde =
         serde_json::from_str(&std::fs::read_to_string(&conf.material_path)?)?;
 
-    let material = material.to_material();
+    let material = material_serde
+        .to_material()
+        .map_err(|e| anyhow::anyhow!("Malformed material: {}", e))?;
     tracing::info!("Material: {:?}", material);
+    material
+        .validate()
+        .map_err(|e| anyhow::anyhow!("Invalid material: {}", e))?;
+    if !material.verify_h() {
+        return Err(anyhow::anyhow!("Material h is not derived from its public seed").into());
+    }
+
+    let x = match (conf.x.as_mut(), conf.domain.as_deref()) {
+        (_, Some(domain)) => {
+            let path = conf
+                .master_secret_path
+                .as_deref()
+                .ok_or_else(|| anyhow::anyhow!("--domain requires --master-secret-path"))?;
+            tracing::info!("Deriving the secret of {} from the master secret", domain);
+            let mut seed = std::fs::read(path)?;
+            let x = derivation::derive_x(&material, &seed, domain);
+            seed.zeroize();
+            x.map_err(|e| anyhow::anyhow!("Key derivation error: {}", e))?
+        }
+        (Some(x), None) => {
+            let secret = BigInt::parse_bytes(x.as_bytes(), 10)
+                .ok_or_else(|| anyhow::anyhow!("BigInt conversion error for x"));
+            x.zeroize();
+            Secret::new(secret?)
+        }
+        (None, None) => {
+            let salt = material_serde
+                .decode_salt()
+                .map_err(|e| anyhow::anyhow!("Malformed material: {}", e))?
+                .ok_or_else(|| {
+                    anyhow::anyhow!("Material has no salt. Regenerate it to use a password.")
+                })?;
+            let mut password = rpassword::prompt_password(format!("Password for {}: ", conf.user))?;
+            let x = password::derive_x(&material, password.as_bytes(), &salt);
+            password.zeroize();
+            x.map_err(|e| anyhow::anyhow!("Password derivation error: {}", e))?
+        }
+    };
 
-    let x = &BigInt::parse_bytes(conf.x.as_bytes(), 10)
-        .ok_or_else(|| anyhow::anyhow!("BigInt conversion error for x"))?;
-
-    let register_zk = Register::new(material.clone(), x);
+    let register_zk = Register::new(material.clone(), x.expose());
 
     let register = zkp_auth::RegisterRequest {
         user: conf.user.to_string(),
-        y1: register_zk.y1.to_i64().ok_or_else(|| {
-            anyhow::anyhow!("BigInt conversion error to i64 for sending result to grpc")
-        })?,
-        y2: register_zk.y2.to_i64().ok_or_else(|| {
-            anyhow::anyhow!("BigInt conversion error to i64 for sending result to grpc")
-        })?,
+        y1: to_bytes(register_zk.y1()),
+        y2: to_bytes(register_zk.y2()),
+        material_fingerprint: material.fingerprint().to_vec(),
     };
     tracing::info!("Registering user: {:?}", register);
     service.register(register).await?;
     tracing::info!("User registered successfully");
 
+    // The nonce is hedged: derived from x, the context of this login and fresh randomness together. The time keeps
+    // the nonces of two logins apart even if the RNG is broken, since the challenge comes after the commitment.
+    let session = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos();
+    let mut transcript = Transcript::new(b"zk-prover/login");
+    transcript.append_material(&material);
+    transcript.append_user(&conf.user);
+    transcript.append_context(conf.prover_address.as_bytes());
+    transcript.append_session(&session.to_be_bytes());
+    transcript.append_element(b"y1", &material, register_zk.y1());
+    transcript.append_element(b"y2", &material, register_zk.y2());
     let challenge = <Register as Into<ProtocolState<_>>>::into(register_zk)
-        .change()
+        .change_hedged(x.expose(), &transcript)
         .into_inner();
+    tracing::debug!("Commitment: {:?}", challenge);
 
     let auth_req = zkp_auth::AuthenticationChallengeRequest {
         user: conf.user.to_string(),
-        r1: challenge.r1.to_i64().ok_or_else(|| {
-            anyhow::anyhow!("BigInt conversion error to i64 for sending result to grpc")
-        })?,
-        r2: challenge.r2.to_i64().ok_or_else(|| {
-            anyhow::anyhow!("BigInt conversion error to i64 for sending result to grpc")
-        })?,
+        r1: to_bytes(challenge.r1()),
+        r2: to_bytes(challenge.r2()),
+        material_fingerprint: material.fingerprint().to_vec(),
     };
     tracing::info!("Sending challenge: {:?}", auth_req);
     let response = service.create_authentication_challenge(auth_req).await?;
@@ -88,10 +154,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let verification = ProtocolState::from(ChallengeResponse {
         challenge: Challenge::builder()
             .auth_id(challenge_response.auth_id)
-            .c(BigInt::from(challenge_response.c))
+            .c(BigInt::from_bytes_be(Sign::Plus, &challenge_response.c))
             .build(),
         material: material.clone(),
-        x: x.clone(),
+        x,
         k: challenge.k,
     })
     .change()
@@ -99,9 +165,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let req = AuthenticationAnswerRequest {
         auth_id: verification.auth_id.to_string(),
-        s: verification.s.to_i32().ok_or_else(|| {
-            anyhow::anyhow!("BigInt conversion error to i32 for sending result to grpc")
-        })?,
+        s: to_bytes(&verification.s),
     };
 
     tracing::info!("Verifying authentication: {:?}", req);
@@ -1,6 +1,6 @@
 use crate::conf::VerifierConfig;
 use crate::domain::verifier::{
-    Answer, AnswerResult, Challenge, ChallengeResponse, ChallengeStore, Params, Register,
+    Answer, AnswerResult, Challenge, ChallengeResponse, ChallengeStore, Params, Register, User,
     VerifierStorage,
 };
 use crate::infrastructure::file_params::FileParams;
@@ -10,6 +10,7 @@ use async_trait::async_trait;
 use mockall::{automock, predicate::*};
 use typed_builder::TypedBuilder;
 use zk_cp_protocol::protocol::cp::{Material, ProtocolState, ProtocolTransition, Verification};
+use zk_cp_protocol::protocol::group::Group;
 
 /// Trait representing a verifier service.
 #[cfg_attr(test, automock)]
@@ -66,11 +67,12 @@ where
         tracing::info!("Registering user: {:?}", register);
         let material = self.params.query(&register.user)?;
 
-        if material.is_none() {
+        let Some(material) = material else {
             return Err(anyhow::anyhow!(
                 "User material not found. You should generate material first."
             ));
-        }
+        };
+        check_fingerprint(&material, &register.user, &register.material_fingerprint)?;
 
         tracing::info!("User material found. Registering user {:?}", register);
         self.storage.store_user(register).await
@@ -82,6 +84,18 @@ where
             .params
             .query(&challenge.user)?
             .ok_or_else(|| anyhow::anyhow!("Material not found"))?;
+        check_fingerprint(&material, &challenge.user, &challenge.material_fingerprint)?;
+
+        if !self.storage.record_commitment(&challenge).await? {
+            tracing::error!(
+                "Commitment reuse detected for user {:?}: answering it again would reveal the secret of the user. \
+                 The client RNG is likely broken.",
+                challenge.user
+            );
+            return Err(anyhow::anyhow!(
+                "Commitment already used. A fresh nonce must be drawn for every authentication."
+            ));
+        }
 
         let created = <Material as Into<ProtocolState<_>>>::into(material)
             .change()
@@ -125,10 +139,8 @@ where
         let verification: ProtocolState<Verification> = Verification::builder()
             .material(material)
             .c(challenge.response.c)
-            .y1(user.y1)
-            .y2(user.y2)
-            .r1(challenge.challenge.r1)
-            .r2(challenge.challenge.r2)
+            .ys(vec![user.y1, user.y2])
+            .rs(vec![challenge.challenge.r1, challenge.challenge.r2])
             .s(answer.s)
             .build()
             .into();
@@ -142,6 +154,22 @@ where
     }
 }
 
+/// Checks that the prover sent the fingerprint of the material the verifier holds for the user.
+///
+/// A prover whose material file differs from the one of the verifier would otherwise only fail the verification.
+fn check_fingerprint(material: &Material, user: &User, fingerprint: &[u8]) -> anyhow::Result<()> {
+    if material.fingerprint() != fingerprint {
+        tracing::error!(
+            "Material fingerprint mismatch for user {:?}: the prover does not use the parameters of the verifier",
+            user
+        );
+        return Err(anyhow::anyhow!(
+            "Material fingerprint mismatch. The material of the prover does not match the parameters of the verifier."
+        ));
+    }
+    Ok(())
+}
+
 impl<M, S> VerifierApplication<M, S>
 where
     M: Params,
@@ -165,6 +193,8 @@ mod tests {
 
     use super::*;
     use crate::domain::verifier::{MockParams, MockVerifierStorage};
+    use zk_cp_protocol::protocol::error::RejectionReason;
+    use zk_cp_protocol::protocol::groups::NamedGroup;
 
     #[tokio::test]
     async fn test_register() {
@@ -180,10 +210,31 @@ mod tests {
             .user("test")
             .y1(BigInt::from(11))
             .y2(BigInt::from(13))
+            .material_fingerprint(Material::default().fingerprint())
             .build();
         assert!(app.register(register).await.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_register_fingerprint_mismatch() {
+        let mut params = MockParams::new();
+        params
+            .expect_query()
+            .times(1)
+            .returning(|_| Ok(Some(Material::default())));
+        let mut storage = MockVerifierStorage::new();
+        storage.expect_store_user().never();
+        let app = VerifierApplication::new(params, storage);
+        let other = Material::from_named_group(NamedGroup::Ffdhe3072);
+        let register = Register::builder()
+            .user("test")
+            .y1(BigInt::from(11))
+            .y2(BigInt::from(13))
+            .material_fingerprint(other.fingerprint())
+            .build();
+        assert!(app.register(register).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_register_error() {
         let mut params = MockParams::new();
@@ -206,6 +257,10 @@ mod tests {
             .times(1)
             .returning(|_| Ok(Some(Material::default())));
         let mut storage = MockVerifierStorage::new();
+        storage
+            .expect_record_commitment()
+            .times(1)
+            .returning(|_| Ok(true));
         storage
             .expect_store_challenge()
             .times(1)
@@ -215,10 +270,53 @@ mod tests {
             .user("test")
             .r1(BigInt::from(11))
             .r2(BigInt::from(13))
+            .material_fingerprint(Material::default().fingerprint())
             .build();
         assert!(app.create_challenge(challenge).await.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_create_challenge_fingerprint_mismatch() {
+        let mut params = MockParams::new();
+        params
+            .expect_query()
+            .times(1)
+            .returning(|_| Ok(Some(Material::default())));
+        let mut storage = MockVerifierStorage::new();
+        storage.expect_record_commitment().never();
+        storage.expect_store_challenge().never();
+        let app = VerifierApplication::new(params, storage);
+        let challenge = Challenge::builder()
+            .user("test")
+            .r1(BigInt::from(11))
+            .r2(BigInt::from(13))
+            .build();
+        assert!(app.create_challenge(challenge).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_create_challenge_commitment_reused() {
+        let mut params = MockParams::new();
+        params
+            .expect_query()
+            .times(1)
+            .returning(|_| Ok(Some(Material::default())));
+        let mut storage = MockVerifierStorage::new();
+        storage
+            .expect_record_commitment()
+            .times(1)
+            .returning(|_| Ok(false));
+        storage.expect_store_challenge().never();
+        let app = VerifierApplication::new(params, storage);
+        let challenge = Challenge::builder()
+            .user("test")
+            .r1(BigInt::from(11))
+            .r2(BigInt::from(13))
+            .material_fingerprint(Material::default().fingerprint())
+            .build();
+        assert!(app.create_challenge(challenge).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_create_challenge_error() {
         let mut params = MockParams::new();
@@ -235,13 +333,64 @@ mod tests {
 
     #[tokio::test]
     async fn test_verify_challenge() {
+        let material = Material::default();
+        let x = BigInt::from(1234);
+        let k = BigInt::from(5678);
+        let c = BigInt::from(87);
+        let (y1, y2) = (material.exp(&material.g, &x), material.exp(&material.h, &x));
+        let (r1, r2) = (material.exp(&material.g, &k), material.exp(&material.h, &k));
+        let s = material.scalar_mul_sub_secret(&k, &c, &x);
+
+        let mut params = MockParams::new();
+        params
+            .expect_query()
+            .times(1)
+            .returning(|_| Ok(Some(Material::default())));
+        let mut storage = MockVerifierStorage::new();
+        storage.expect_get_challenge().times(1).returning(move |_| {
+            Ok(Some(
+                ChallengeStore::builder()
+                    .challenge(
+                        Challenge::builder()
+                            .r1(r1.clone())
+                            .r2(r2.clone())
+                            .user("test")
+                            .build(),
+                    )
+                    .response(
+                        ChallengeResponse::builder()
+                            .auth_id("test")
+                            .c(c.clone())
+                            .build(),
+                    )
+                    .build(),
+            ))
+        });
+        storage.expect_get_user().times(1).returning(move |_| {
+            Ok(Some(
+                Register::builder()
+                    .y1(y1.clone())
+                    .y2(y2.clone())
+                    .user("test")
+                    .build(),
+            ))
+        });
+        let app = VerifierApplication::new(params, storage);
+        let answer = Answer::builder().auth_id("test").s(s).build();
+        let result = app.verify_challenge(answer).await.unwrap();
+        assert!(matches!(result, AnswerResult::Success(_)));
+    }
+
+    #[tokio::test]
+    async fn test_verify_challenge_trivial_material() {
         let mut params = MockParams::new();
         let material = Material::builder()
-            .p(BigInt::from(1))
+            .p(BigInt::from(3))
             .q(BigInt::from(1))
             .g(BigInt::from(1))
             .h(BigInt::from(1))
-            .build();
+            .build()
+            .unwrap();
         params
             .expect_query()
             .times(1)
@@ -277,7 +426,12 @@ mod tests {
         });
         let app = VerifierApplication::new(params, storage);
         let answer = Answer::builder().auth_id("test").s(BigInt::from(1)).build();
-        assert!(app.verify_challenge(answer).await.is_ok());
+        let result = app.verify_challenge(answer).await.unwrap();
+        // The generators are the identity, which is not a valid base.
+        assert_eq!(
+            result,
+            AnswerResult::Rejected(RejectionReason::NotInGroup("bases"))
+        );
     }
 
     #[tokio::test]
@@ -293,8 +447,8 @@ mod tests {
                 ChallengeStore::builder()
                     .challenge(
                         Challenge::builder()
-                            .r1(BigInt::from(18))
-                            .r2(BigInt::from(16))
+                            .r1(BigInt::from(16))
+                            .r2(BigInt::from(36))
                             .user("test")
                             .build(),
                     )
@@ -310,8 +464,8 @@ mod tests {
         storage.expect_get_user().times(1).returning(|_| {
             Ok(Some(
                 Register::builder()
-                    .y1(BigInt::from(22))
-                    .y2(BigInt::from(54))
+                    .y1(BigInt::from(64))
+                    .y2(BigInt::from(100))
                     .user("test")
                     .build(),
             ))
@@ -324,4 +478,52 @@ mod tests {
         let result = app.verify_challenge(answer).await.unwrap();
         assert_eq!(result, AnswerResult::Failure);
     }
+
+    #[tokio::test]
+    async fn test_verify_challenge_rejected() {
+        let mut params = MockParams::new();
+        params
+            .expect_query()
+            .times(1)
+            .returning(|_| Ok(Some(Material::default())));
+        let mut storage = MockVerifierStorage::new();
+        storage.expect_get_challenge().times(1).returning(|_| {
+            Ok(Some(
+                ChallengeStore::builder()
+                    .challenge(
+                        Challenge::builder()
+                            .r1(Material::default().p - 1)
+                            .r2(BigInt::from(36))
+                            .user("test")
+                            .build(),
+                    )
+                    .response(
+                        ChallengeResponse::builder()
+                            .auth_id("test")
+                            .c(BigInt::from(87))
+                            .build(),
+                    )
+                    .build(),
+            ))
+        });
+        storage.expect_get_user().times(1).returning(|_| {
+            Ok(Some(
+                Register::builder()
+                    .y1(BigInt::from(64))
+                    .y2(BigInt::from(100))
+                    .user("test")
+                    .build(),
+            ))
+        });
+        let app = VerifierApplication::new(params, storage);
+        let answer = Answer::builder()
+            .auth_id("test")
+            .s(BigInt::from(11))
+            .build();
+        let result = app.verify_challenge(answer).await.unwrap();
+        assert_eq!(
+            result,
+            AnswerResult::Rejected(RejectionReason::NotInGroup("r1"))
+        );
+    }
 }
@@ -1,12 +1,15 @@
 use config::{Config, File};
 use serde::{Deserialize, Serialize};
 use typed_builder::TypedBuilder;
+use zk_cp_protocol::protocol::groups::NamedGroup;
 
 #[derive(TypedBuilder, Deserialize, Serialize, Clone, Default)]
 pub struct VerifierConfig {
     pub port: u16,
     pub response_timeout_in_secs: u64,
     pub material: String,
+    /// Named group used for the users in the material file that do not define their own parameters.
+    pub group: Option<NamedGroup>,
 }
 
 #[derive(TypedBuilder)]
@@ -60,5 +63,6 @@ mod tests {
         assert_eq!(conf.port, 50_000);
         assert_eq!(conf.response_timeout_in_secs, 60);
         assert_eq!(conf.material, "../protocol/data/server_material.json");
+        assert_eq!(conf.group, None);
     }
 }
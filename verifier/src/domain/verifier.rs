@@ -4,14 +4,24 @@ use crate::grpc::zkp_auth::{
 };
 #[cfg(test)]
 use mockall::{automock, predicate::*};
-use num_bigint::BigInt;
-use num_traits::ToPrimitive;
+use num_bigint::{BigInt, Sign};
 use serde::{Deserialize, Serialize};
 use std::ops::Deref;
 use tonic::Status;
 use typed_builder::TypedBuilder;
 use uuid::Uuid;
 use zk_cp_protocol::protocol::cp::{AuthId, Material};
+use zk_cp_protocol::protocol::error::RejectionReason;
+
+/// Decodes an unsigned big-endian integer received through gRPC.
+fn from_bytes(bytes: &[u8]) -> BigInt {
+    BigInt::from_bytes_be(Sign::Plus, bytes)
+}
+
+/// Encodes an integer as unsigned big-endian bytes for sending through gRPC.
+fn to_bytes(n: &BigInt) -> Vec<u8> {
+    n.magnitude().to_bytes_be()
+}
 
 #[derive(Debug, Clone, TypedBuilder)]
 pub struct Register {
@@ -21,14 +31,18 @@ pub struct Register {
     pub y1: BigInt,
     #[builder(setter(into))]
     pub y2: BigInt,
+    /// The `Group::fingerprint` of the material of the prover.
+    #[builder(default, setter(into))]
+    pub material_fingerprint: Vec<u8>,
 }
 
 impl From<RegisterRequest> for Register {
     fn from(request: RegisterRequest) -> Self {
         Self {
             user: request.user.into(),
-            y1: request.y1.into(),
-            y2: request.y2.into(),
+            y1: from_bytes(&request.y1),
+            y2: from_bytes(&request.y2),
+            material_fingerprint: request.material_fingerprint,
         }
     }
 }
@@ -41,14 +55,18 @@ pub struct Challenge {
     pub r1: BigInt,
     #[builder(setter(into))]
     pub r2: BigInt,
+    /// The `Group::fingerprint` of the material of the prover.
+    #[builder(default, setter(into))]
+    pub material_fingerprint: Vec<u8>,
 }
 
 impl From<AuthenticationChallengeRequest> for Challenge {
     fn from(request: AuthenticationChallengeRequest) -> Self {
         Self {
             user: request.user.into(),
-            r1: request.r1.into(),
-            r2: request.r2.into(),
+            r1: from_bytes(&request.r1),
+            r2: from_bytes(&request.r2),
+            material_fingerprint: request.material_fingerprint,
         }
     }
 }
@@ -61,16 +79,12 @@ pub struct ChallengeResponse {
     pub c: BigInt,
 }
 
-impl TryFrom<ChallengeResponse> for AuthenticationChallengeResponse {
-    type Error = anyhow::Error;
-    fn try_from(response: ChallengeResponse) -> anyhow::Result<Self> {
-        let c = response.c.to_i32().ok_or_else(|| {
-            anyhow::anyhow!("BigInt conversion error to i64 for sending result to grpc")
-        })?;
-        Ok(Self {
+impl From<ChallengeResponse> for AuthenticationChallengeResponse {
+    fn from(response: ChallengeResponse) -> Self {
+        Self {
             auth_id: response.auth_id.to_string(),
-            c,
-        })
+            c: to_bytes(&response.c),
+        }
     }
 }
 
@@ -78,7 +92,7 @@ impl From<AuthenticationChallengeResponse> for ChallengeResponse {
     fn from(response: AuthenticationChallengeResponse) -> Self {
         Self {
             auth_id: response.auth_id.into(),
-            c: response.c.into(),
+            c: from_bytes(&response.c),
         }
     }
 }
@@ -110,7 +124,7 @@ impl From<AuthenticationAnswerRequest> for Answer {
     fn from(request: AuthenticationAnswerRequest) -> Self {
         Self {
             auth_id: request.auth_id.into(),
-            s: request.s.into(),
+            s: from_bytes(&request.s),
         }
     }
 }
@@ -124,6 +138,7 @@ pub struct Success {
 pub enum AnswerResult {
     Success(Success),
     Failure,
+    Rejected(RejectionReason),
 }
 
 impl From<zk_cp_protocol::protocol::cp::VerificationResult> for AnswerResult {
@@ -137,6 +152,9 @@ impl From<zk_cp_protocol::protocol::cp::VerificationResult> for AnswerResult {
             zk_cp_protocol::protocol::cp::VerificationResult::ChallengeVerificationFailed => {
                 Self::Failure
             }
+            zk_cp_protocol::protocol::cp::VerificationResult::ChallengeRejected(reason) => {
+                Self::Rejected(reason)
+            }
         }
     }
 }
@@ -149,6 +167,10 @@ impl TryFrom<AnswerResult> for AuthenticationAnswerResponse {
                 session_id: success.session_id.0,
             }),
             AnswerResult::Failure => Err(Status::invalid_argument("Challenge verification failed")),
+            AnswerResult::Rejected(reason) => Err(Status::invalid_argument(format!(
+                "Challenge verification rejected: {}",
+                reason
+            ))),
         }
     }
 }
@@ -239,4 +261,19 @@ pub trait VerifierStorage {
     /// Returns `Ok(Some(challenge))` if the challenge is found, `Ok(None)` if the challenge is not found,
     /// otherwise returns an `anyhow::Error`.
     async fn get_challenge(&self, auth_id: &AuthId) -> anyhow::Result<Option<ChallengeStore>>;
+
+    /// Asynchronously records the commitment `(r1, r2)` a user sent to start an authentication.
+    ///
+    /// A commitment sent twice means the client reused its nonce `k`, and two answers to it reveal the secret of the
+    /// user. Implementations may only remember a bounded number of recent commitments per user.
+    ///
+    /// # Arguments
+    ///
+    /// * `challenge` - The challenge request holding the user and the commitment.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(true)` if the user never sent this commitment before, `Ok(false)` if it is a reused one,
+    /// otherwise returns an `anyhow::Error`.
+    async fn record_commitment(&self, challenge: &Challenge) -> anyhow::Result<bool>;
 }
@@ -98,13 +98,7 @@ where
                         e.to_string()
                     ))
                 })?;
-        let resp = challenge_started.try_into().map_err(|e: anyhow::Error| {
-            tonic::Status::internal(format!(
-                "Error converting challenge response: {:?}",
-                e.to_string()
-            ))
-        })?;
-        Ok(tonic::Response::new(resp))
+        Ok(tonic::Response::new(challenge_started.into()))
     }
 
     async fn verify_authentication(
@@ -1,4 +1,5 @@
 use zk_cp_protocol::protocol::cp::{Material, MaterialSerde};
+use zk_cp_protocol::protocol::group::Group;
 
 use crate::conf::VerifierConfig;
 use crate::domain::verifier::{Params, User};
@@ -12,6 +13,13 @@ pub struct FileParams {
 impl FileParams {
     /// Creates a new instance of `FileParams` by loading the materials from the specified file path.
     ///
+    /// Entries that neither name a group nor carry explicit parameters use the named group from the configuration.
+    /// Every entry is checked with `Material::validate`, and its generator `h` is re-derived from its public seed. The
+    /// whole file is rejected if any entry fails these checks.
+    ///
+    /// Users with the same parameters share a single `Material`, whose fixed-base tables are built here once and then
+    /// reused by every request.
+    ///
     /// # Arguments
     ///
     /// * `conf` - The verifier configuration containing the file path to load the materials from.
@@ -22,10 +30,35 @@ impl FileParams {
     pub fn new(conf: &VerifierConfig) -> anyhow::Result<Self> {
         let materials: Vec<MaterialSerde> =
             serde_json::from_str(&std::fs::read_to_string(&conf.material)?)?;
+        let mut shared: HashMap<Vec<u8>, Material> = HashMap::new();
         let materials = materials
             .into_iter()
-            .map(|m| (User(m.user.clone()), m.to_material()))
-            .collect();
+            .map(|mut m| {
+                if m.p.is_none() && m.group.is_none() {
+                    m.group = conf.group;
+                }
+                let material = m.to_material().map_err(|e| {
+                    anyhow::anyhow!("Malformed material for user {:?}: {}", m.user, e)
+                })?;
+                material.validate().map_err(|e| {
+                    anyhow::anyhow!("Invalid material for user {:?}: {}", m.user, e)
+                })?;
+                if !material.verify_h() {
+                    return Err(anyhow::anyhow!(
+                        "Material for user {:?} has an h that is not derived from its public seed",
+                        m.user
+                    ));
+                }
+                let material = shared
+                    .entry(material.encode_params())
+                    .or_insert_with(|| {
+                        material.precompute();
+                        material
+                    })
+                    .clone();
+                Ok((User(m.user.clone()), material))
+            })
+            .collect::<anyhow::Result<_>>()?;
         Ok(Self { materials })
     }
 }
@@ -44,3 +77,57 @@ impl Params for FileParams {
         Ok(self.materials.get(user).cloned())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zk_cp_protocol::protocol::groups::NamedGroup;
+
+    fn load(name: &str, content: &str, group: Option<NamedGroup>) -> anyhow::Result<FileParams> {
+        let path = std::env::temp_dir().join(format!("zk_verifier_{}.json", name));
+        std::fs::write(&path, content)?;
+        let conf = VerifierConfig::builder()
+            .port(0)
+            .response_timeout_in_secs(0)
+            .material(path.to_string_lossy().to_string())
+            .group(group)
+            .build();
+        FileParams::new(&conf)
+    }
+
+    #[test]
+    fn test_load_named_group() {
+        let params = load(
+            "named",
+            r#"[{"user":"named","group":"ffdhe2048","h_seed":"seed"},{"user":"default"}]"#,
+            Some(NamedGroup::Modp2048),
+        )
+        .unwrap();
+        let named = params.query(&User::from("named")).unwrap().unwrap();
+        assert_eq!(named.group, Some(NamedGroup::Ffdhe2048));
+        let default = params.query(&User::from("default")).unwrap().unwrap();
+        assert_eq!(default.group, Some(NamedGroup::Modp2048));
+    }
+
+    #[test]
+    fn test_load_invalid_material() {
+        let material = MaterialSerde::from_material(&Material::generate(64, None).unwrap(), "user");
+        let invalid = MaterialSerde {
+            q: material.p.clone(),
+            ..material
+        };
+        let content = serde_json::to_string(&vec![invalid]).unwrap();
+        assert!(load("invalid", &content, None).is_err());
+    }
+
+    #[test]
+    fn test_load_unseeded_h() {
+        let material = MaterialSerde::from_material(&Material::generate(64, None).unwrap(), "user");
+        let unseeded = MaterialSerde {
+            h_seed: None,
+            ..material
+        };
+        let content = serde_json::to_string(&vec![unseeded]).unwrap();
+        assert!(load("unseeded", &content, None).is_err());
+    }
+}
@@ -1,11 +1,18 @@
-use crate::domain::verifier::{ChallengeStore, Register, User, VerifierStorage};
+use crate::domain::verifier::{Challenge, ChallengeStore, Register, User, VerifierStorage};
 use dashmap::DashMap;
+use num_bigint::BigInt;
+use std::collections::VecDeque;
 use zk_cp_protocol::protocol::cp::AuthId;
 
+/// Number of the most recent commitments kept per user to detect reuse.
+pub(crate) const MAX_COMMITMENTS_PER_USER: usize = 1024;
+
 /// In-memory storage implementation for the verifier module.
 pub(crate) struct MemStorage {
     pub(crate) users: DashMap<User, Register>,
     pub(crate) challenges: DashMap<AuthId, ChallengeStore>,
+    /// The last `MAX_COMMITMENTS_PER_USER` commitments of every user, from the oldest to the newest.
+    pub(crate) commitments: DashMap<User, VecDeque<(BigInt, BigInt)>>,
 }
 
 impl MemStorage {
@@ -14,6 +21,7 @@ impl MemStorage {
         Self {
             users: DashMap::new(),
             challenges: DashMap::new(),
+            commitments: DashMap::new(),
         }
     }
 }
@@ -80,4 +88,84 @@ impl VerifierStorage for MemStorage {
     async fn get_challenge(&self, auth_id: &AuthId) -> anyhow::Result<Option<ChallengeStore>> {
         Ok(self.challenges.get(auth_id).map(|c| c.value().clone()))
     }
+
+    /// Records a commitment in the memory storage. Only the last `MAX_COMMITMENTS_PER_USER` commitments of each user
+    /// are kept, the oldest one being dropped when a new one is recorded, so reuse is detected within that window.
+    /// A stuck client RNG repeats its commitment on the next authentication, well within it.
+    ///
+    /// # Arguments
+    ///
+    /// * `challenge` - The challenge request holding the user and the commitment.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(true)` if the commitment is new for the user, or `Ok(false)` if the user already sent it.
+    async fn record_commitment(&self, challenge: &Challenge) -> anyhow::Result<bool> {
+        let commitment = (challenge.r1.clone(), challenge.r2.clone());
+        let mut history = self.commitments.entry(challenge.user.clone()).or_default();
+        if history.contains(&commitment) {
+            return Ok(false);
+        }
+        if history.len() == MAX_COMMITMENTS_PER_USER {
+            history.pop_front();
+        }
+        history.push_back(commitment);
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn challenge(user: &str, r1: u64, r2: u64) -> Challenge {
+        Challenge::builder().user(user).r1(r1).r2(r2).build()
+    }
+
+    #[tokio::test]
+    async fn test_record_commitment() {
+        let storage = MemStorage::new();
+        assert!(storage
+            .record_commitment(&challenge("user", 1, 2))
+            .await
+            .unwrap());
+        assert!(!storage
+            .record_commitment(&challenge("user", 1, 2))
+            .await
+            .unwrap());
+        // The same commitment is new for another user, and another commitment is new for the same user.
+        assert!(storage
+            .record_commitment(&challenge("other", 1, 2))
+            .await
+            .unwrap());
+        assert!(storage
+            .record_commitment(&challenge("user", 2, 1))
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_record_commitment_bounded() {
+        let storage = MemStorage::new();
+        for r in 0..=MAX_COMMITMENTS_PER_USER as u64 {
+            assert!(storage
+                .record_commitment(&challenge("user", r, r))
+                .await
+                .unwrap());
+        }
+        assert_eq!(
+            storage.commitments.get(&User::from("user")).unwrap().len(),
+            MAX_COMMITMENTS_PER_USER
+        );
+        // The oldest commitment was dropped, the newest ones are still detected.
+        assert!(storage
+            .record_commitment(&challenge("user", 0, 0))
+            .await
+            .unwrap());
+        let last = MAX_COMMITMENTS_PER_USER as u64;
+        assert!(!storage
+            .record_commitment(&challenge("user", last, last))
+            .await
+            .unwrap());
+    }
 }